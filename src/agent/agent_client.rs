@@ -0,0 +1,25 @@
+use crate::client::sts::sts_client::Credentials;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+#[async_trait]
+pub trait AgentClient {
+    /// Gets the session credentials held by the agent under **key**, if any.
+    async fn get_creds(&self, key: &str) -> Result<Option<Credentials>>;
+
+    /// Hands the given session **creds** to the agent to hold under **key**, replacing any session it holds.
+    async fn put_creds(&self, key: &str, creds: &Credentials) -> Result<()>;
+
+    /// Gets the agent's status.
+    async fn status(&self) -> Result<AgentStatus>;
+
+    /// Stops the agent.
+    async fn stop(&self) -> Result<()>;
+}
+
+#[derive(Debug)]
+pub struct AgentStatus {
+    pub idle_timeout_secs: u64,
+    pub session_expiration: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,131 @@
+use crate::client::sts::sts_client::{Credentials, Creds};
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use std::{
+    fs::{self, Permissions},
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    path::PathBuf,
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    time::timeout,
+};
+
+/// How long a client has to send its request and read the response.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct AgentServer {
+    socket_path: PathBuf,
+    idle_timeout: Duration,
+    /// The session held, with the key it was handed over under.
+    creds: Option<(String, Credentials)>,
+}
+
+impl AgentServer {
+    pub fn new(socket_path: PathBuf, idle_timeout: Duration) -> AgentServer {
+        AgentServer {
+            socket_path,
+            idle_timeout,
+            creds: None,
+        }
+    }
+
+    /// Serves requests on the agent socket until stopped or until no request has been received for the idle timeout.
+    pub async fn run(mut self) -> Result<()> {
+        if UnixStream::connect(&self.socket_path).await.is_ok() {
+            return Err(anyhow!(
+                "An agent is already listening on {}",
+                self.socket_path.display()
+            ));
+        }
+        if self.socket_path.exists() {
+            info!("Removing stale agent socket {}", self.socket_path.display());
+            fs::remove_file(&self.socket_path)?;
+        }
+        let mut listener = UnixListener::bind(&self.socket_path)?;
+        fs::set_permissions(&self.socket_path, Permissions::from_mode(0o600))?;
+        let owner_uid = fs::metadata(&self.socket_path)?.uid();
+        info!("Agent listening on {}", self.socket_path.display());
+        loop {
+            let stream = match timeout(self.idle_timeout, listener.accept()).await {
+                Ok(Ok((stream, _))) => stream,
+                Ok(Err(e)) => {
+                    warn!("Failed to accept agent connection: {}", e);
+                    continue;
+                }
+                Err(_) => {
+                    info!("Agent idle for {:?}, exiting", self.idle_timeout);
+                    break;
+                }
+            };
+            match stream.peer_cred() {
+                Ok(cred) if cred.uid == owner_uid => {}
+                Ok(_) => {
+                    warn!("Refusing agent connection from another user");
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Failed to check agent connection's user: {}", e);
+                    continue;
+                }
+            }
+            // A client that never completes its request mustn't block the others
+            match timeout(REQUEST_TIMEOUT, self.handle(stream)).await {
+                Ok(Ok(true)) => continue,
+                Ok(Ok(false)) => break,
+                Ok(Err(e)) => warn!("Failed to handle agent request: {}", e),
+                Err(_) => warn!("Agent request timed out after {:?}", REQUEST_TIMEOUT),
+            }
+        }
+        fs::remove_file(&self.socket_path)?;
+        Ok(())
+    }
+
+    /// Handles a single request on the given **stream**, returns whether the agent should keep running.
+    async fn handle(&mut self, stream: UnixStream) -> Result<bool> {
+        let mut reader = BufReader::new(stream);
+        let mut request = String::new();
+        reader.read_line(&mut request).await?;
+        if self.creds.as_ref().is_some_and(|(_, c)| c.is_expired()) {
+            info!("Dropping expired session credentials");
+            self.creds = None;
+        }
+        let request = request.trim_end();
+        let (response, keep_running) = match request.split_once(' ').unwrap_or((request, "")) {
+            // Only the session of the same key is handed out, others were obtained for other credentials or endpoints
+            ("GET", key) => match &self.creds {
+                Some((held, creds)) if held == key => (format!("CREDS {}", creds.to_line()), true),
+                _ => ("NONE".to_string(), true),
+            },
+            ("PUT", rest) => match rest
+                .split_once(' ')
+                .map(|(key, line)| (key, Credentials::from_line(line)))
+            {
+                Some((key, Ok(creds))) => {
+                    info!("Holding session credentials expiring at {}", creds.expiration);
+                    self.creds = Some((key.to_string(), creds));
+                    ("OK".to_string(), true)
+                }
+                Some((_, Err(e))) => (format!("ERR {}", e), true),
+                None => ("ERR Malformed PUT request".to_string(), true),
+            },
+            ("STATUS", _) => (
+                format!(
+                    "STATUS {} {}",
+                    self.idle_timeout.as_secs(),
+                    self.creds
+                        .as_ref()
+                        .map_or("none".to_string(), |(_, c)| c.expiration.to_rfc3339())
+                ),
+                true,
+            ),
+            ("STOP", _) => ("OK".to_string(), false),
+            (command, _) => (format!("ERR Unknown command {}", command), true),
+        };
+        let mut stream = reader.into_inner();
+        stream.write_all(format!("{}\n", response).as_bytes()).await?;
+        Ok(keep_running)
+    }
+}
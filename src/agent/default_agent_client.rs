@@ -0,0 +1,73 @@
+use super::agent_client::{AgentClient, AgentStatus};
+use crate::client::sts::sts_client::Credentials;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::info;
+use std::{net::Shutdown, path::PathBuf};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::UnixStream,
+};
+
+pub struct DefaultAgentClient {
+    socket_path: PathBuf,
+}
+
+impl DefaultAgentClient {
+    pub fn new(socket_path: PathBuf) -> impl AgentClient {
+        DefaultAgentClient { socket_path }
+    }
+
+    /// Sends a single **request** line to the agent and returns its single line response. Neither the request nor
+    /// the response is logged as either may carry credentials.
+    async fn send(&self, request: &str) -> Result<String> {
+        let mut stream = UnixStream::connect(&self.socket_path).await?;
+        stream.write_all(format!("{}\n", request).as_bytes()).await?;
+        stream.shutdown(Shutdown::Write)?;
+        let mut response = String::new();
+        BufReader::new(stream).read_line(&mut response).await?;
+        let response = response.trim_end().to_string();
+        match response.strip_prefix("ERR ") {
+            Some(message) => Err(anyhow!("Agent error: {}", message)),
+            None => Ok(response),
+        }
+    }
+}
+
+#[async_trait]
+impl AgentClient for DefaultAgentClient {
+    async fn get_creds(&self, key: &str) -> Result<Option<Credentials>> {
+        info!("Will request creds from agent at {}", self.socket_path.display());
+        let response = self.send(&format!("GET {}", key)).await?;
+        match response.strip_prefix("CREDS ") {
+            Some(line) => Ok(Some(Credentials::from_line(line)?)),
+            None if response == "NONE" => Ok(None),
+            None => Err(anyhow!("Unexpected agent response")),
+        }
+    }
+
+    async fn put_creds(&self, key: &str, creds: &Credentials) -> Result<()> {
+        info!("Will hand creds to agent at {}", self.socket_path.display());
+        self.send(&format!("PUT {} {}", key, creds.to_line())).await.map(|_| ())
+    }
+
+    async fn status(&self) -> Result<AgentStatus> {
+        let response = self.send("STATUS").await?;
+        let parts: Vec<&str> = response.split_whitespace().collect();
+        match parts.as_slice() {
+            ["STATUS", idle_timeout, expiration] => Ok(AgentStatus {
+                idle_timeout_secs: idle_timeout.parse()?,
+                session_expiration: match *expiration {
+                    "none" => None,
+                    e => Some(DateTime::parse_from_rfc3339(e)?.with_timezone(&Utc)),
+                },
+            }),
+            _ => Err(anyhow!("Unexpected agent response")),
+        }
+    }
+
+    async fn stop(&self) -> Result<()> {
+        self.send("STOP").await.map(|_| ())
+    }
+}
@@ -0,0 +1,95 @@
+pub mod agent_client;
+pub mod agent_server;
+pub mod default_agent_client;
+
+use crate::output;
+use agent_client::AgentClient;
+use agent_server::AgentServer;
+use anyhow::{anyhow, Result};
+use default_agent_client::DefaultAgentClient;
+use serde_json::{json, Value};
+use std::{
+    env, fs,
+    os::unix::process::CommandExt,
+    path::Path,
+    process::{Command, Stdio},
+    time::Duration,
+};
+use tokio::time::delay_for;
+
+pub const AGENT_SOCKET_FILENAME: &str = ".agent.sock";
+
+/// Starts the agent for the given **store_dir** as a detached background process.
+pub async fn start(store_dir: &Path, idle_timeout: u64) -> Result<()> {
+    let agent_client = DefaultAgentClient::new(store_dir.join(AGENT_SOCKET_FILENAME));
+    if agent_client.status().await.is_ok() {
+        return report("Agent already running", json!({"running": true, "started": false}));
+    }
+    Command::new(env::current_exe()?)
+        .args(["agent", "run", "--idle-timeout", &idle_timeout.to_string()])
+        .env("PASSWORD_STORE_DIR", store_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()?;
+    for _ in 0..20 {
+        delay_for(Duration::from_millis(100)).await;
+        if agent_client.status().await.is_ok() {
+            return report("Agent started", json!({"running": true, "started": true}));
+        }
+    }
+    Err(anyhow!("Agent did not start, try `aws-pass agent run` to see why"))
+}
+
+/// Stops the agent for the given **store_dir**.
+pub async fn stop(store_dir: &Path) -> Result<()> {
+    let agent_client = DefaultAgentClient::new(store_dir.join(AGENT_SOCKET_FILENAME));
+    let stopped = agent_client.stop().await.is_ok();
+    let message = if stopped { "Agent stopped" } else { "Agent not running" };
+    report(message, json!({"running": false, "stopped": stopped}))
+}
+
+/// Prints the status of the agent for the given **store_dir**.
+pub async fn status(store_dir: &Path) -> Result<()> {
+    let agent_client = DefaultAgentClient::new(store_dir.join(AGENT_SOCKET_FILENAME));
+    let status = agent_client.status().await;
+    if !output::is_text() {
+        let status = status.ok();
+        return output::print(&json!({
+            "running": status.is_some(),
+            "idle_timeout": status.as_ref().map(|s| s.idle_timeout_secs),
+            "session_expiration": output::date(status.and_then(|s| s.session_expiration)),
+        }));
+    }
+    match status {
+        Ok(status) => {
+            println!("Agent running, idle timeout {}s", status.idle_timeout_secs);
+            match status.session_expiration {
+                Some(expiration) => println!("Session expires at {}", expiration),
+                None => println!("No session"),
+            }
+        }
+        Err(_) => println!("Agent not running"),
+    }
+    Ok(())
+}
+
+/// Runs the agent for the given **store_dir** in the foreground.
+pub async fn run(store_dir: &Path, idle_timeout: u64) -> Result<()> {
+    if !store_dir.is_dir() {
+        fs::create_dir_all(store_dir)?;
+    }
+    AgentServer::new(store_dir.join(AGENT_SOCKET_FILENAME), Duration::from_secs(idle_timeout))
+        .run()
+        .await
+}
+
+/// Prints the outcome of starting or stopping the agent, **message** as text and **value** in other formats.
+fn report(message: &str, value: Value) -> Result<()> {
+    if !output::is_text() {
+        return output::print(&value);
+    }
+    println!("{}", message);
+    Ok(())
+}
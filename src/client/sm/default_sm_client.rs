@@ -1,11 +1,15 @@
-use super::sm_client::{Filter, SecretDetails, SecretString, SecretsPage, SmClient, Tag};
+use super::sm_client::{
+    ExcludedClasses, Filter, SecretDetails, SecretString, SecretVersion, SecretsPage, SmClient, Tag,
+};
+use crate::error::{from_rusoto, AwsPassError};
 use anyhow::Result;
 use async_trait::async_trait;
 use log::info;
 use rusoto_core::{credential, HttpClient, Region};
 use rusoto_secretsmanager::{
     CreateSecretRequest, DeleteSecretRequest, DescribeSecretRequest, GetRandomPasswordRequest, GetSecretValueRequest,
-    ListSecretsRequest, PutSecretValueRequest, SecretsManager, SecretsManagerClient,
+    ListSecretVersionIdsRequest, ListSecretsRequest, PutSecretValueRequest, RestoreSecretRequest, SecretsManager,
+    SecretsManagerClient, UpdateSecretVersionStageRequest,
 };
 use uuid::Uuid;
 
@@ -30,13 +34,22 @@ impl DefaultSmClient {
 
 #[async_trait]
 impl SmClient for DefaultSmClient {
-    async fn create_secret_string(&self, name: &str, value: &str, tags: Option<&[Tag]>) -> Result<String> {
+    async fn create_secret_string(
+        &self,
+        name: &str,
+        value: &str,
+        description: Option<&str>,
+        tags: Option<&[Tag]>,
+        kms_key_id: Option<&str>,
+    ) -> Result<String> {
         let create_secret_request = CreateSecretRequest {
             client_request_token: Some(Uuid::new_v4().to_string()),
+            description: description.map(|d| d.to_string()),
+            kms_key_id: kms_key_id.map(|k| k.to_string()),
             name: name.to_string(),
             secret_string: Some(value.to_string()),
             tags: tags.map(|ts| {
-                ts.into_iter()
+                ts.iter()
                     .map(|t| rusoto_secretsmanager::Tag {
                         key: Some(t.0.clone()),
                         value: Some(t.1.clone()),
@@ -48,51 +61,146 @@ impl SmClient for DefaultSmClient {
         info!("Will send create secret request {:?}", create_secret_request);
         let create_secret_response = self.sm_client.create_secret(create_secret_request).await;
         info!("Did receive create secret response {:?}", create_secret_response);
-        Ok(create_secret_response?.arn.unwrap())
+        Ok(create_secret_response.map_err(|e| from_rusoto(e, name))?.arn.unwrap())
     }
 
-    async fn delete_secret(&self, arn: &str) -> Result<()> {
+    async fn delete_secret(&self, arn: &str, recovery_days: Option<i64>, force: bool) -> Result<Option<f64>> {
         let delete_secret_request = DeleteSecretRequest {
             secret_id: arn.to_string(),
-            ..Default::default()
+            force_delete_without_recovery: Some(force).filter(|f| *f),
+            recovery_window_in_days: recovery_days,
         };
         info!("Will send delete secret request {:?}", delete_secret_request);
         let delete_secret_response = self.sm_client.delete_secret(delete_secret_request).await;
         info!("Did receive delete secret response {:?}", delete_secret_response);
-        Ok(delete_secret_response?).map(|_| ())
+        delete_secret_response
+            .map(|r| r.deletion_date)
+            .map_err(|e| from_rusoto(e, arn))
+    }
+
+    async fn restore_secret(&self, arn: &str) -> Result<()> {
+        let restore_secret_request = RestoreSecretRequest {
+            secret_id: arn.to_string(),
+        };
+        info!("Will send restore secret request {:?}", restore_secret_request);
+        let restore_secret_response = self.sm_client.restore_secret(restore_secret_request).await;
+        info!("Did receive restore secret response {:?}", restore_secret_response);
+        restore_secret_response.map(|_| ()).map_err(|e| from_rusoto(e, arn))
     }
 
     async fn describe_secret(&self, arn: &str) -> Result<SecretDetails> {
         let describe_secret_request = DescribeSecretRequest {
             secret_id: arn.to_string(),
-            ..Default::default()
         };
         info!("Will send describe secret request {:?}", describe_secret_request);
         let describe_secret_response = self.sm_client.describe_secret(describe_secret_request).await;
         info!("Did receive describe secret result {:?}", describe_secret_response);
-        Ok(describe_secret_response?).map(|s| SecretDetails {
-            arn: s.arn.unwrap(),
-            name: s.name.unwrap(),
-            tags: translate_tags(s.tags),
-            description: s.description,
-        })
+        describe_secret_response
+            .map_err(|e| from_rusoto(e, arn))
+            .map(|s| SecretDetails {
+                arn: s.arn.unwrap(),
+                name: s.name.unwrap(),
+                tags: translate_tags(s.tags),
+                description: s.description,
+                kms_key_id: s.kms_key_id,
+                deleted_date: s.deleted_date,
+                created_date: s.created_date,
+                last_changed_date: s.last_changed_date,
+                last_accessed_date: s.last_accessed_date,
+            })
     }
 
-    async fn get_secret_string(&self, arn: &str) -> Result<SecretString> {
+    async fn get_secret_string(&self, arn: &str, version_id: Option<&str>) -> Result<SecretString> {
         let get_secret_value_request = GetSecretValueRequest {
             secret_id: arn.to_string(),
-            ..Default::default()
+            version_id: version_id.map(|id| id.to_string()),
+            version_stage: None,
         };
         info!("Will send get secret value request {:?}", get_secret_value_request);
         let get_secret_value_response = self.sm_client.get_secret_value(get_secret_value_request).await;
         info!("Did receive get secret value response {:?}", get_secret_value_response);
-        Ok(get_secret_value_response?).map(|s| SecretString {
+        let s = get_secret_value_response.map_err(|e| from_rusoto(e, arn))?;
+        let value = s.secret_string.ok_or_else(|| {
+            AwsPassError::InvalidInput(format!("{} holds a binary secret, which aws-pass can't show", arn))
+        })?;
+        Ok(SecretString {
             arn: s.arn.unwrap(),
-            name: s.name.unwrap(),
-            value: s.secret_string.unwrap(),
+            value,
         })
     }
 
+    async fn list_secret_version_ids(&self, arn: &str) -> Result<Vec<SecretVersion>> {
+        let mut versions = Vec::new();
+        let mut next_token: Option<String> = None;
+        loop {
+            let list_secret_version_ids_request = ListSecretVersionIdsRequest {
+                include_deprecated: Some(true),
+                next_token: next_token.clone(),
+                secret_id: arn.to_string(),
+                ..Default::default()
+            };
+            info!(
+                "Will send list secret version ids request {:?}",
+                list_secret_version_ids_request
+            );
+            let list_secret_version_ids_response = self
+                .sm_client
+                .list_secret_version_ids(list_secret_version_ids_request)
+                .await;
+            info!(
+                "Did receive list secret version ids response {:?}",
+                list_secret_version_ids_response
+            );
+            let response = list_secret_version_ids_response.map_err(|e| from_rusoto(e, arn))?;
+            versions.extend(
+                response
+                    .versions
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|v| SecretVersion {
+                        version_id: v.version_id.unwrap(),
+                        stages: v.version_stages.unwrap_or_default(),
+                        created_date: v.created_date,
+                    }),
+            );
+            next_token = response.next_token;
+            if next_token.is_none() {
+                break;
+            }
+        }
+        Ok(versions)
+    }
+
+    async fn update_secret_version_stage(
+        &self,
+        arn: &str,
+        stage: &str,
+        move_to: &str,
+        remove_from: Option<&str>,
+    ) -> Result<()> {
+        let update_secret_version_stage_request = UpdateSecretVersionStageRequest {
+            move_to_version_id: Some(move_to.to_string()),
+            remove_from_version_id: remove_from.map(|v| v.to_string()),
+            secret_id: arn.to_string(),
+            version_stage: stage.to_string(),
+        };
+        info!(
+            "Will send update secret version stage request {:?}",
+            update_secret_version_stage_request
+        );
+        let update_secret_version_stage_response = self
+            .sm_client
+            .update_secret_version_stage(update_secret_version_stage_request)
+            .await;
+        info!(
+            "Did receive update secret version stage response {:?}",
+            update_secret_version_stage_response
+        );
+        update_secret_version_stage_response
+            .map(|_| ())
+            .map_err(|e| from_rusoto(e, arn))
+    }
+
     async fn list_secrets(&self, filters: Option<&[Filter]>, next_token: Option<&str>) -> Result<SecretsPage> {
         let list_secrets_request = ListSecretsRequest {
             filters: filters.map(|fs| {
@@ -107,10 +215,9 @@ impl SmClient for DefaultSmClient {
             ..Default::default()
         };
         info!("Will send list secrets request {:?}", list_secrets_request);
-        // TODO: Use better error handling
         let list_secrets_response = self.sm_client.list_secrets(list_secrets_request).await;
         info!("Did receive list secrets response {:?}", list_secrets_response);
-        Ok(list_secrets_response?).map(|lsr| {
+        list_secrets_response.map_err(|e| from_rusoto(e, "secrets")).map(|lsr| {
             (
                 lsr.secret_list.map_or(Vec::new(), |sl| {
                     sl.into_iter()
@@ -119,6 +226,11 @@ impl SmClient for DefaultSmClient {
                             name: s.name.unwrap(),
                             tags: translate_tags(s.tags),
                             description: s.description,
+                            kms_key_id: s.kms_key_id,
+                            deleted_date: s.deleted_date,
+                            created_date: s.created_date,
+                            last_changed_date: s.last_changed_date,
+                            last_accessed_date: s.last_accessed_date,
                         })
                         .collect()
                 }),
@@ -137,20 +249,36 @@ impl SmClient for DefaultSmClient {
         info!("Will send put secret value request {:?}", put_secret_value_request);
         let put_secret_value_response = self.sm_client.put_secret_value(put_secret_value_request).await;
         info!("Did receive put secret value response {:?}", put_secret_value_response);
-        Ok(put_secret_value_response?).map(|_| ())
+        put_secret_value_response.map(|_| ()).map_err(|e| from_rusoto(e, arn))
     }
 
-    async fn get_random_password(&self, exclude_chars: Option<&str>, length: Option<&i64>) -> Result<String> {
+    async fn get_random_password(
+        &self,
+        exclude_chars: Option<&str>,
+        length: Option<&i64>,
+        excluded_classes: ExcludedClasses,
+        require_each_included_type: bool,
+    ) -> Result<String> {
+        let (exclude_lowercase, exclude_uppercase, exclude_numbers, exclude_punctuation) = excluded_classes;
         let get_random_password_request = GetRandomPasswordRequest {
-            exclude_characters: exclude_chars.map(|s| s.to_string()),
-            password_length: length.map(|l| l.clone()),
+            exclude_characters: exclude_chars.map(String::from),
+            exclude_lowercase: Some(exclude_lowercase),
+            exclude_uppercase: Some(exclude_uppercase),
+            exclude_numbers: Some(exclude_numbers),
+            exclude_punctuation: Some(exclude_punctuation),
+            password_length: length.copied(),
+            require_each_included_type: Some(require_each_included_type),
             ..Default::default()
         };
         info!(
             "Will send get random password request {:?}",
             get_random_password_request
         );
-        let get_random_password_response = self.sm_client.get_random_password(get_random_password_request).await?;
+        let get_random_password_response = self
+            .sm_client
+            .get_random_password(get_random_password_request)
+            .await
+            .map_err(|e| from_rusoto(e, "random password"))?;
         info!(
             "Did receive get random password response {:?}",
             get_random_password_response
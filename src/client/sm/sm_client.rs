@@ -3,18 +3,42 @@ use async_trait::async_trait;
 
 #[async_trait]
 pub trait SmClient {
-    /// Creates a secret string given a **name** and a **value**, returns the
-    /// created secret's **arn**.
-    async fn create_secret_string(&self, name: &str, value: &str, tags: Option<&[Tag]>) -> Result<String>;
+    /// Creates a secret string given a **name**, a **value** and an optional **description**, encrypted with the
+    /// optional **kms_key_id**, returns the created secret's **arn**.
+    async fn create_secret_string(
+        &self,
+        name: &str,
+        value: &str,
+        description: Option<&str>,
+        tags: Option<&[Tag]>,
+        kms_key_id: Option<&str>,
+    ) -> Result<String>;
 
-    /// Deletes the secret for the given **arn**.
-    async fn delete_secret(&self, arn: &str) -> Result<()>;
+    /// Deletes the secret for the given **arn** after a recovery window of **recovery_days**, 30 by default, or
+    /// without one when **force** is set. Returns the date the secret will be deleted, in seconds since the epoch.
+    async fn delete_secret(&self, arn: &str, recovery_days: Option<i64>, force: bool) -> Result<Option<f64>>;
+
+    /// Cancels the deletion of the secret for the given **arn**, within its recovery window.
+    async fn restore_secret(&self, arn: &str) -> Result<()>;
 
     /// Describes the secret for the given **arn**.
     async fn describe_secret(&self, arn: &str) -> Result<SecretDetails>;
 
-    /// Gets the secret for the given **arn**.
-    async fn get_secret_string(&self, arn: &str) -> Result<SecretString>;
+    /// Gets the secret for the given **arn**, its current version unless a **version** is given.
+    async fn get_secret_string(&self, arn: &str, version_id: Option<&str>) -> Result<SecretString>;
+
+    /// Lists the versions of the secret for the given **arn**, including deprecated ones without a stage.
+    async fn list_secret_version_ids(&self, arn: &str) -> Result<Vec<SecretVersion>>;
+
+    /// Moves the **stage** of the secret for the given **arn** to the version **move_to**, removing it from the
+    /// version **remove_from**.
+    async fn update_secret_version_stage(
+        &self,
+        arn: &str,
+        stage: &str,
+        move_to: &str,
+        remove_from: Option<&str>,
+    ) -> Result<()>;
 
     /// Lists secrets for the given optional **filters** and **next_token**.
     /// Returns a page with its results and next token.
@@ -23,13 +47,22 @@ pub trait SmClient {
     /// Puts a secret string for the given **arn** and secret **value**.
     async fn put_secret_string(&self, arn: &str, value: &str) -> Result<()>;
 
-    /// Generates a random password
-    async fn get_random_password(&self, exclude_chars: Option<&str>, length: Option<&i64>) -> Result<String>;
+    /// Generates a random password of **length** without the **exclude_chars** and the excluded character classes,
+    /// with a character of each included class when **require_each_included_type** is set.
+    async fn get_random_password(
+        &self,
+        exclude_chars: Option<&str>,
+        length: Option<&i64>,
+        excluded_classes: ExcludedClasses,
+        require_each_included_type: bool,
+    ) -> Result<String>;
 }
 
 pub type SecretsPage = (Vec<SecretDetails>, Option<String>);
 pub type Tag = (String, String);
 pub type Filter = (String, Vec<String>);
+/// Whether lowercase letters, uppercase letters, numbers and punctuation are left out of a random password.
+pub type ExcludedClasses = (bool, bool, bool, bool);
 
 #[derive(Debug)]
 pub struct SecretDetails {
@@ -37,11 +70,24 @@ pub struct SecretDetails {
     pub name: String,
     pub tags: Vec<(String, String)>,
     pub description: Option<String>,
+    pub kms_key_id: Option<String>,
+    /// When the secret was removed, in seconds since the epoch, set while it's pending deletion.
+    pub deleted_date: Option<f64>,
+    pub created_date: Option<f64>,
+    pub last_changed_date: Option<f64>,
+    pub last_accessed_date: Option<f64>,
+}
+
+#[derive(Debug)]
+pub struct SecretVersion {
+    pub version_id: String,
+    pub stages: Vec<String>,
+    /// Seconds since the epoch.
+    pub created_date: Option<f64>,
 }
 
 #[derive(Debug)]
 pub struct SecretString {
     pub arn: String,
-    pub name: String,
     pub value: String,
 }
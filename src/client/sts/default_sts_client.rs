@@ -1,5 +1,6 @@
 use super::sts_client::{Credentials, StsClient};
-use anyhow::Result;
+use crate::error::{from_rusoto, AwsPassError};
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use chrono::{NaiveDateTime, TimeZone, Utc};
 use log::info;
@@ -41,17 +42,23 @@ impl StsClient for DefaultStsClient {
         info!("Will send get session token request {:?}", get_session_token_request);
         let get_session_token_response = self.sts_client.get_session_token(get_session_token_request).await;
         info!("Did receive session token response {:?}", get_session_token_response);
-        get_session_token_response?
+        let c = get_session_token_response
+            .map_err(|e| {
+                // STS answers a wrong or reused token code with access denied
+                let err = from_rusoto(e, "session token");
+                match err.downcast::<AwsPassError>() {
+                    Ok(AwsPassError::AccessDenied(message)) => AwsPassError::MfaFailed(message).into(),
+                    Ok(other) => other.into(),
+                    Err(err) => err,
+                }
+            })?
             .credentials
-            .map(|c| {
-                Ok(Credentials {
-                    access_key_id: c.access_key_id,
-                    secret_access_key: c.secret_access_key,
-                    session_token: c.session_token,
-                    expiration: Utc
-                        .from_utc_datetime(&NaiveDateTime::parse_from_str(&c.expiration, "%Y-%m-%dT%H:%M:%S%Z")?),
-                })
-            })
-            .unwrap()
+            .ok_or_else(|| anyhow!("Session token response carried no credentials"))?;
+        Ok(Credentials {
+            access_key_id: c.access_key_id,
+            secret_access_key: c.secret_access_key,
+            session_token: c.session_token,
+            expiration: Utc.from_utc_datetime(&NaiveDateTime::parse_from_str(&c.expiration, "%Y-%m-%dT%H:%M:%S%Z")?),
+        })
     }
 }
@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use rusoto_core::credential::AwsCredentials;
@@ -36,7 +36,44 @@ impl Creds for Credentials {
             self.access_key_id.clone(),
             self.secret_access_key.clone(),
             Some(self.session_token.clone()),
-            Some(self.expiration.clone()),
+            Some(self.expiration),
         )
     }
 }
+
+impl Credentials {
+    /// Serializes the credentials to a single space-delimited line.
+    pub fn to_line(&self) -> String {
+        format!(
+            "{} {} {} {}",
+            self.access_key_id,
+            self.secret_access_key,
+            self.session_token,
+            self.expiration.to_rfc3339()
+        )
+    }
+
+    /// Converts the given **aws_creds** back into session credentials, if they carry a token and an expiration.
+    pub fn from_aws_creds(aws_creds: &AwsCredentials) -> Option<Credentials> {
+        Some(Credentials {
+            access_key_id: aws_creds.aws_access_key_id().to_string(),
+            secret_access_key: aws_creds.aws_secret_access_key().to_string(),
+            session_token: aws_creds.token().clone()?,
+            expiration: (*aws_creds.expires_at())?,
+        })
+    }
+
+    /// Parses credentials from a line written by **to_line**.
+    pub fn from_line(line: &str) -> Result<Credentials> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() != 4 {
+            return Err(anyhow!("Malformed credentials line"));
+        }
+        Ok(Credentials {
+            access_key_id: parts[0].to_string(),
+            secret_access_key: parts[1].to_string(),
+            session_token: parts[2].to_string(),
+            expiration: DateTime::parse_from_rfc3339(parts[3])?.with_timezone(&Utc),
+        })
+    }
+}
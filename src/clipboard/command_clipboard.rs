@@ -0,0 +1,94 @@
+use super::Clipboard;
+use crate::{error::AwsPassError, util::find_on_path};
+use anyhow::{anyhow, Result};
+use log::info;
+use std::{
+    env,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// A clipboard driven by external copy and paste commands.
+pub struct CommandClipboard {
+    copy_cmd: Vec<String>,
+    paste_cmd: Vec<String>,
+}
+
+impl CommandClipboard {
+    /// Picks the clipboard commands, preferring `AWS_PASS_CLIP_COPY` and `AWS_PASS_CLIP_PASTE`, then Wayland's
+    /// `wl-copy`, then X11's `xclip` or `xsel`.
+    pub fn detect() -> Result<CommandClipboard> {
+        if let (Ok(copy_cmd), Ok(paste_cmd)) = (env::var("AWS_PASS_CLIP_COPY"), env::var("AWS_PASS_CLIP_PASTE")) {
+            return Ok(CommandClipboard::new(&copy_cmd, &paste_cmd));
+        }
+        if env::var_os("WAYLAND_DISPLAY").is_some() && find_on_path("wl-copy").is_some() {
+            return Ok(CommandClipboard::new("wl-copy", "wl-paste --no-newline"));
+        }
+        if env::var_os("DISPLAY").is_some() {
+            if find_on_path("xclip").is_some() {
+                return Ok(CommandClipboard::new(
+                    "xclip -selection clipboard",
+                    "xclip -selection clipboard -o",
+                ));
+            }
+            if find_on_path("xsel").is_some() {
+                return Ok(CommandClipboard::new(
+                    "xsel --clipboard --input",
+                    "xsel --clipboard --output",
+                ));
+            }
+        }
+        Err(AwsPassError::InvalidInput(
+            "No clipboard found, install wl-clipboard, xclip or xsel or set AWS_PASS_CLIP_COPY and AWS_PASS_CLIP_PASTE"
+                .to_string(),
+        )
+        .into())
+    }
+
+    fn new(copy_cmd: &str, paste_cmd: &str) -> CommandClipboard {
+        CommandClipboard {
+            copy_cmd: copy_cmd.split_whitespace().map(String::from).collect(),
+            paste_cmd: paste_cmd.split_whitespace().map(String::from).collect(),
+        }
+    }
+}
+
+impl Clipboard for CommandClipboard {
+    fn copy(&self, value: &str) -> Result<()> {
+        let (program, args) = self
+            .copy_cmd
+            .split_first()
+            .ok_or_else(|| anyhow!("Empty copy command"))?;
+        info!("Will copy to clipboard with {:?}", self.copy_cmd);
+        // The copy commands keep serving the selection in the background, they must not hold on to our output
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("Could not write to {}", program))?
+            .write_all(value.as_bytes())?;
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(anyhow!("{} failed with {}", program, status));
+        }
+        Ok(())
+    }
+
+    fn paste(&self) -> Result<String> {
+        let (program, args) = self
+            .paste_cmd
+            .split_first()
+            .ok_or_else(|| anyhow!("Empty paste command"))?;
+        info!("Will paste from clipboard with {:?}", self.paste_cmd);
+        let output = Command::new(program).args(args).stderr(Stdio::null()).output()?;
+        if !output.status.success() {
+            return Err(anyhow!("{} failed with {}", program, output.status));
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+}
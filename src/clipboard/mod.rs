@@ -0,0 +1,150 @@
+pub mod command_clipboard;
+
+use anyhow::{anyhow, Result};
+use command_clipboard::CommandClipboard;
+use std::{
+    env,
+    io::{self, Read, Write},
+    os::unix::process::CommandExt,
+    process::{Child, Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+pub trait Clipboard {
+    /// Puts the given **value** on the clipboard.
+    fn copy(&self, value: &str) -> Result<()>;
+
+    /// Gets the clipboard's current contents.
+    fn paste(&self) -> Result<String>;
+}
+
+/// Puts the given **value** on the clipboard and spawns a detached helper that restores the previous clipboard contents
+/// after **timeout** seconds, like `pass -c`.
+pub fn copy_with_restore(value: &str, timeout: u64) -> Result<()> {
+    let mut helper = Command::new(env::current_exe()?);
+    helper.args(["clip-restore", "--timeout", &timeout.to_string()]);
+    copy_with_helper(value, &mut helper).map(|_| ())
+}
+
+/// Puts the given **value** on the clipboard and hands it with the previous contents to the restoring **helper**.
+fn copy_with_helper(value: &str, helper: &mut Command) -> Result<Child> {
+    let clipboard = CommandClipboard::detect()?;
+    let previous = clipboard.paste().unwrap_or_default();
+    clipboard.copy(value)?;
+    let mut helper = helper
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()?;
+    // Values are handed over on stdin so they never show up in the process list
+    let mut stdin = helper
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("Could not talk to clipboard helper"))?;
+    stdin.write_all(format!("{} {}\n{}{}", value.len(), previous.len(), value, previous).as_bytes())?;
+    Ok(helper)
+}
+
+/// Runs the helper spawned by **copy_with_restore**, restoring the previous clipboard contents after **timeout**
+/// seconds unless the clipboard has changed since.
+pub fn restore_after(timeout: u64) -> Result<()> {
+    restore(io::stdin(), Duration::from_secs(timeout))
+}
+
+/// Restores the previous clipboard contents handed over on **input** after **timeout**.
+fn restore<R: Read>(mut input: R, timeout: Duration) -> Result<()> {
+    let mut handed_over = String::new();
+    input.read_to_string(&mut handed_over)?;
+    let (header, contents) = handed_over
+        .split_once('\n')
+        .ok_or_else(|| anyhow!("Malformed clipboard helper input"))?;
+    let (value_len, previous_len) = header
+        .split_once(' ')
+        .ok_or_else(|| anyhow!("Malformed clipboard helper input"))?;
+    let value_len: usize = value_len.parse()?;
+    let previous_len: usize = previous_len.parse()?;
+    if contents.len() != value_len + previous_len || !contents.is_char_boundary(value_len) {
+        return Err(anyhow!("Malformed clipboard helper input"));
+    }
+    let (value, previous) = contents.split_at(value_len);
+    thread::sleep(timeout);
+    let clipboard = CommandClipboard::detect()?;
+    if clipboard.paste()? == value {
+        clipboard.copy(previous)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, os::unix::fs::PermissionsExt, path::Path, sync::Mutex};
+    use tempfile::TempDir;
+
+    /// The fake clipboard is configured through the environment, which the tests share.
+    static CLIPBOARD_ENV: Mutex<()> = Mutex::new(());
+
+    /// Sets up copy and paste commands keeping the clipboard in a file, holding **contents**.
+    fn fake_clipboard(dir: &Path, contents: &str) {
+        for (name, script) in [
+            ("copy", "cat > \"$(dirname \"$0\")/clipboard\""),
+            ("paste", "cat \"$(dirname \"$0\")/clipboard\""),
+        ] {
+            let path = dir.join(name);
+            fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        fs::write(dir.join("clipboard"), contents).unwrap();
+        env::set_var("AWS_PASS_CLIP_COPY", dir.join("copy"));
+        env::set_var("AWS_PASS_CLIP_PASTE", dir.join("paste"));
+    }
+
+    /// Copies **value** with a helper that saves what it's handed over, returning that.
+    fn copy(dir: &Path, value: &str) -> Vec<u8> {
+        let handed_over = dir.join("handed-over");
+        let mut helper = Command::new("sh");
+        helper.args(["-c", &format!("cat > {}", handed_over.display())]);
+        copy_with_helper(value, &mut helper).unwrap().wait().unwrap();
+        fs::read(handed_over).unwrap()
+    }
+
+    fn clipboard(dir: &Path) -> String {
+        fs::read_to_string(dir.join("clipboard")).unwrap()
+    }
+
+    #[test]
+    fn restores_previous_contents() {
+        let _env = CLIPBOARD_ENV.lock().unwrap();
+        let dir = TempDir::new().unwrap();
+        fake_clipboard(dir.path(), "previous\nlines");
+        let handed_over = copy(dir.path(), "sécret");
+        assert_eq!(clipboard(dir.path()), "sécret");
+        assert_eq!(handed_over, "7 14\nsécretprevious\nlines".as_bytes());
+        restore(handed_over.as_slice(), Duration::ZERO).unwrap();
+        assert_eq!(clipboard(dir.path()), "previous\nlines");
+    }
+
+    #[test]
+    fn keeps_contents_copied_since() {
+        let _env = CLIPBOARD_ENV.lock().unwrap();
+        let dir = TempDir::new().unwrap();
+        fake_clipboard(dir.path(), "previous");
+        let handed_over = copy(dir.path(), "secret");
+        fs::write(dir.path().join("clipboard"), "copied since").unwrap();
+        restore(handed_over.as_slice(), Duration::ZERO).unwrap();
+        assert_eq!(clipboard(dir.path()), "copied since");
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let _env = CLIPBOARD_ENV.lock().unwrap();
+        let dir = TempDir::new().unwrap();
+        fake_clipboard(dir.path(), "previous");
+        for input in ["", "6 8", "6\nsecret", "7 8\nsecretprevious", "1 1\néa"] {
+            assert!(restore(input.as_bytes(), Duration::ZERO).is_err(), "{:?}", input);
+        }
+        assert_eq!(clipboard(dir.path()), "previous");
+    }
+}
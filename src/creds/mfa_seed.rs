@@ -0,0 +1,127 @@
+use crate::{
+    crypto::{self, EncryptionKey},
+    otp::{self, Algorithm},
+    util::{read_first_line, write_private_file},
+};
+use anyhow::Result;
+use log::info;
+use std::{fs, path::PathBuf, time::Duration};
+use tokio::time::delay_for;
+
+/// The period of the virtual MFA device's time based codes, in seconds.
+const PERIOD: u64 = 30;
+/// Codes are not used this close to the end of their window, in seconds, to leave time for the request to reach STS.
+const MIN_REMAINING: u64 = 3;
+
+/// The encrypted TOTP seed of a virtual MFA device, used to compute token codes without a prompt. Like the session
+/// cache it's encrypted with a key derived from the long-lived secret access key and bound to the token serial.
+pub struct MfaSeed {
+    path: PathBuf,
+    last_step_path: PathBuf,
+    key: EncryptionKey,
+    token_serial: String,
+}
+
+impl MfaSeed {
+    pub fn new(path: PathBuf, last_step_path: PathBuf, secret_access_key: &str, token_serial: &str) -> MfaSeed {
+        MfaSeed {
+            path,
+            last_step_path,
+            key: crypto::derive_key(secret_access_key, "aws-pass mfa seed"),
+            token_serial: token_serial.to_string(),
+        }
+    }
+
+    /// Stores the given **seed**, the raw bytes of the device's secret.
+    pub fn store(&self, seed: &[u8]) -> Result<()> {
+        let data = crypto::encrypt(&self.key, seed, self.token_serial.as_bytes())?;
+        write_private_file(&self.path, &data)?;
+        Ok(())
+    }
+
+    pub fn remove(&self) -> Result<()> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        if self.last_step_path.exists() {
+            fs::remove_file(&self.last_step_path)?;
+        }
+        Ok(())
+    }
+
+    /// Computes a token code STS hasn't seen yet. STS rejects a code it has already accepted, so when the current
+    /// window's code has been used, or the window is about to end, this waits for the next window.
+    pub async fn token_code(&self) -> Result<String> {
+        let seed = crypto::decrypt(&self.key, &fs::read(&self.path)?, self.token_serial.as_bytes())?;
+        let last_step: Option<u64> = match self.last_step_path.exists() {
+            true => read_first_line(&self.last_step_path)?.and_then(|l| l.parse().ok()),
+            false => None,
+        };
+        loop {
+            let now = otp::now();
+            let step = now / PERIOD;
+            let remaining = PERIOD - now % PERIOD;
+            if last_step.is_some_and(|ls| step <= ls) || remaining < MIN_REMAINING {
+                info!("Waiting {}s for the next MFA window", remaining);
+                delay_for(Duration::from_secs(remaining)).await;
+                continue;
+            }
+            write_private_file(&self.last_step_path, step.to_string().as_bytes())?;
+            return Ok(otp::hotp(&seed, step, 6, Algorithm::Sha1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const SEED: &[u8] = b"12345678901234567890";
+    const TOKEN_SERIAL: &str = "arn:aws:iam::123456789012:mfa/alice";
+
+    fn mfa_seed(dir: &TempDir, secret_access_key: &str, token_serial: &str) -> MfaSeed {
+        MfaSeed::new(
+            dir.path().join("mfa-seed"),
+            dir.path().join("mfa-last-step"),
+            secret_access_key,
+            token_serial,
+        )
+    }
+
+    #[tokio::test]
+    async fn computes_the_code_of_the_window_it_records() {
+        let dir = TempDir::new().unwrap();
+        let mfa_seed = mfa_seed(&dir, "secret", TOKEN_SERIAL);
+        mfa_seed.store(SEED).unwrap();
+        let code = mfa_seed.token_code().await.unwrap();
+        let step: u64 = fs::read_to_string(dir.path().join("mfa-last-step"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(step, otp::now() / PERIOD);
+        assert_eq!(code, otp::hotp(SEED, step, 6, Algorithm::Sha1));
+    }
+
+    #[tokio::test]
+    async fn seed_is_bound_to_the_key_and_token_serial() {
+        let dir = TempDir::new().unwrap();
+        mfa_seed(&dir, "secret", TOKEN_SERIAL).store(SEED).unwrap();
+        assert!(mfa_seed(&dir, "other secret", TOKEN_SERIAL).token_code().await.is_err());
+        assert!(mfa_seed(&dir, "secret", "arn:aws:iam::123456789012:mfa/bob")
+            .token_code()
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn remove_deletes_seed_and_last_step() {
+        let dir = TempDir::new().unwrap();
+        let mfa_seed = mfa_seed(&dir, "secret", TOKEN_SERIAL);
+        mfa_seed.store(SEED).unwrap();
+        fs::write(dir.path().join("mfa-last-step"), "1").unwrap();
+        mfa_seed.remove().unwrap();
+        assert!(!dir.path().join("mfa-seed").exists());
+        assert!(!dir.path().join("mfa-last-step").exists());
+    }
+}
@@ -1,48 +1,158 @@
+pub mod mfa_seed;
+pub mod session_cache;
+
 use crate::{
+    agent::{agent_client::AgentClient, default_agent_client::DefaultAgentClient},
     client::sts::{
         default_sts_client::DefaultStsClient,
         sts_client::{Credentials, Creds, StsClient},
     },
-    util::{prompt_stdin_line, read_first_line},
+    error::AwsPassError,
+    prompt,
+    util::read_first_line,
 };
+use anyhow::Result;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::info;
+use mfa_seed::MfaSeed;
 use rusoto_core::{
     credential::{AwsCredentials, CredentialsError, ProfileProvider, ProvideAwsCredentials},
     Region,
 };
-use std::{path::PathBuf, sync::Arc};
+use session_cache::SessionCache;
+use sha2::{Digest, Sha256};
+use std::{
+    path::{Path, PathBuf},
+    sync::{self, Arc},
+};
 use tokio::sync::Mutex;
 
+/// The shortest session STS will issue, in seconds.
+pub const MIN_SESSION_DURATION: i64 = 900;
+/// The longest session STS will issue to an IAM user, in seconds.
+pub const MAX_SESSION_DURATION: i64 = 129_600;
+
+/// The local files the MFA credentials provider works with.
+pub struct LocalMfaFiles {
+    /// The long-lived credentials, in the AWS credentials file format.
+    pub creds_path: PathBuf,
+    pub token_serial_path: PathBuf,
+    pub session_cache_path: PathBuf,
+    pub mfa_seed_path: PathBuf,
+    pub mfa_last_step_path: PathBuf,
+}
+
 pub struct StsLocalMfaCredsProvider {
     cached_creds: Arc<Mutex<Option<Credentials>>>,
-    token_serial_path: PathBuf,
+    files: LocalMfaFiles,
+    profile: String,
+    sts_endpoint: Option<String>,
+    session_duration: i64,
     sts_client: Box<dyn StsClient + Send + Sync>,
 }
 
 impl StsLocalMfaCredsProvider {
-    pub fn new(creds_path: PathBuf, token_serial_path: PathBuf, region: &Region) -> impl ProvideAwsCredentials {
+    pub fn new(
+        files: LocalMfaFiles,
+        profile: &str,
+        sts_endpoint: Option<&str>,
+        session_duration: i64,
+        region: &Region,
+    ) -> impl ProvideAwsCredentials {
         StsLocalMfaCredsProvider {
             cached_creds: Arc::new(Mutex::new(None)),
-            token_serial_path,
             sts_client: Box::new(DefaultStsClient::new(
-                ProfileProvider::with_default_configuration(creds_path),
+                ProfileProvider::with_configuration(&files.creds_path, profile),
                 region,
             )),
+            files,
+            profile: profile.to_string(),
+            sts_endpoint: sts_endpoint.map(String::from),
+            session_duration,
         }
     }
 }
 
 impl StsLocalMfaCredsProvider {
-    async fn get_creds(&self) -> Credentials {
-        let serial_number = read_first_line(&self.token_serial_path).unwrap();
-        let token_code = prompt_stdin_line("MFA token code:");
-        self.sts_client
-            .get_session_token(Some(&900), Some(&serial_number), Some(&token_code))
-            .await
-            .unwrap()
+    async fn get_creds(&self) -> Result<Credentials> {
+        let serial_number = read_token_serial(&self.files.token_serial_path)?;
+        let session_cache = self.session_cache(&serial_number).await;
+        if let Some(creds) = session_cache.as_ref().and_then(|sc| sc.load()) {
+            info!("Using cached session expiring at {}", creds.expiration);
+            return Ok(creds);
+        }
+        let token_code = if self.files.mfa_seed_path.exists() {
+            info!("Computing MFA token code from the stored seed");
+            MfaSeed::new(
+                self.files.mfa_seed_path.clone(),
+                self.files.mfa_last_step_path.clone(),
+                &read_secret_access_key(&self.files.creds_path, &self.profile).await?,
+                &serial_number,
+            )
+            .token_code()
+            .await?
+        } else {
+            prompt::ask_hidden("mfa-token-code", "MFA token code:")?
+        };
+        let creds = self
+            .sts_client
+            .get_session_token(Some(&self.session_duration), Some(&serial_number), Some(&token_code))
+            .await?;
+        if let Some(Err(e)) = session_cache.map(|sc| sc.store(&creds)) {
+            info!("Could not cache session: {}", e);
+        }
+        Ok(creds)
+    }
+
+    /// Opens the session cache for the given **token_serial**, keyed by the long-lived secret access key and bound to
+    /// the profile and STS endpoint.
+    async fn session_cache(&self, token_serial: &str) -> Option<SessionCache> {
+        match read_secret_access_key(&self.files.creds_path, &self.profile).await {
+            Ok(secret_access_key) => Some(SessionCache::new(
+                self.files.session_cache_path.clone(),
+                &secret_access_key,
+                &self.profile,
+                token_serial,
+                self.sts_endpoint.as_deref(),
+            )),
+            Err(e) => {
+                info!("Session cache unavailable: {}", e);
+                None
+            }
+        }
     }
 }
 
+/// Reads the long-lived secret access key of **profile** from the credentials file at **creds_path**.
+pub async fn read_secret_access_key(creds_path: &Path, profile: &str) -> Result<String> {
+    Ok(read_profile_creds(creds_path, profile)
+        .await?
+        .aws_secret_access_key()
+        .to_string())
+}
+
+/// Reads the long-lived access key id of **profile** from the credentials file at **creds_path**.
+pub async fn read_access_key_id(creds_path: &Path, profile: &str) -> Result<String> {
+    Ok(read_profile_creds(creds_path, profile)
+        .await?
+        .aws_access_key_id()
+        .to_string())
+}
+
+async fn read_profile_creds(creds_path: &Path, profile: &str) -> Result<AwsCredentials> {
+    Ok(ProfileProvider::with_configuration(creds_path, profile)
+        .credentials()
+        .await
+        .map_err(|e| AwsPassError::InvalidInput(format!("Could not read {}: {}", creds_path.display(), e)))?)
+}
+
+/// Reads the MFA token serial from the file at **token_serial_path**.
+pub fn read_token_serial(token_serial_path: &PathBuf) -> Result<String> {
+    Ok(read_first_line(token_serial_path)?
+        .ok_or_else(|| AwsPassError::InvalidInput("MFA token serial file is empty".to_string()))?)
+}
+
 #[async_trait]
 impl ProvideAwsCredentials for StsLocalMfaCredsProvider {
     async fn credentials(&self) -> Result<AwsCredentials, CredentialsError> {
@@ -50,8 +160,114 @@ impl ProvideAwsCredentials for StsLocalMfaCredsProvider {
         if mutex.is_some() && !mutex.as_ref().unwrap().is_expired() {
             return Ok(mutex.as_ref().unwrap().to_aws_creds());
         }
-        let new_creds = self.get_creds().await;
+        let new_creds = self
+            .get_creds()
+            .await
+            .map_err(|e| CredentialsError::new(format!("{:#}", e)))?;
         *mutex = Some(new_creds.clone());
-        return Ok(new_creds.to_aws_creds());
+        Ok(new_creds.to_aws_creds())
+    }
+}
+
+/// Provides the session credentials held by the agent, falling back to the **inner** provider when the agent isn't
+/// running or holds no session. Credentials obtained from the fallback are handed to the agent for later commands.
+/// Sessions are keyed on the profile, its access key id and the STS endpoint, so that a session is only reused with
+/// the credentials and endpoint that obtained it.
+pub struct AgentCredsProvider<P> {
+    agent_client: Box<dyn AgentClient + Send + Sync>,
+    creds_path: PathBuf,
+    profile: String,
+    sts_endpoint: Option<String>,
+    inner: P,
+}
+
+impl<P> AgentCredsProvider<P>
+where
+    P: ProvideAwsCredentials + Send + Sync,
+{
+    pub fn new(
+        socket_path: PathBuf,
+        creds_path: PathBuf,
+        profile: &str,
+        sts_endpoint: Option<&str>,
+        inner: P,
+    ) -> impl ProvideAwsCredentials {
+        AgentCredsProvider {
+            agent_client: Box::new(DefaultAgentClient::new(socket_path)),
+            creds_path,
+            profile: profile.to_string(),
+            sts_endpoint: sts_endpoint.map(String::from),
+            inner,
+        }
+    }
+
+    /// The key the agent holds the session under, a digest as the agent only compares keys.
+    async fn session_key(&self) -> Result<String> {
+        let access_key_id = read_access_key_id(&self.creds_path, &self.profile).await?;
+        let key = format!(
+            "{}\n{}\n{}",
+            self.profile,
+            access_key_id,
+            self.sts_endpoint.as_deref().unwrap_or_default()
+        );
+        Ok(format!("{:x}", Sha256::digest(key.as_bytes())))
+    }
+}
+
+#[async_trait]
+impl<P> ProvideAwsCredentials for AgentCredsProvider<P>
+where
+    P: ProvideAwsCredentials + Send + Sync,
+{
+    async fn credentials(&self) -> Result<AwsCredentials, CredentialsError> {
+        let key = self
+            .session_key()
+            .await
+            .map_err(|e| CredentialsError::new(format!("{:#}", e)))?;
+        match self.agent_client.get_creds(&key).await {
+            Ok(Some(creds)) if !creds.is_expired() => return Ok(creds.to_aws_creds()),
+            Ok(_) => info!("Agent holds no session"),
+            Err(e) => info!("Agent unavailable: {}", e),
+        }
+        let aws_creds = self.inner.credentials().await?;
+        if let Some(creds) = Credentials::from_aws_creds(&aws_creds) {
+            if let Err(e) = self.agent_client.put_creds(&key, &creds).await {
+                info!("Could not hand session to agent: {}", e);
+            }
+        }
+        Ok(aws_creds)
+    }
+}
+
+/// Shares one credentials provider, and with it one MFA session, between clients, e.g. of stores in different regions.
+#[derive(Clone)]
+pub struct SharedCredsProvider {
+    inner: Arc<dyn ProvideAwsCredentials + Send + Sync>,
+    expiration: Arc<sync::Mutex<Option<DateTime<Utc>>>>,
+}
+
+impl SharedCredsProvider {
+    pub fn new<P>(inner: P) -> SharedCredsProvider
+    where
+        P: ProvideAwsCredentials + Send + Sync + 'static,
+    {
+        SharedCredsProvider {
+            inner: Arc::new(inner),
+            expiration: Arc::new(sync::Mutex::new(None)),
+        }
+    }
+
+    /// When the credentials last provided expire, without obtaining any, none before the first are provided.
+    pub fn expiration(&self) -> Option<DateTime<Utc>> {
+        *self.expiration.lock().unwrap()
+    }
+}
+
+#[async_trait]
+impl ProvideAwsCredentials for SharedCredsProvider {
+    async fn credentials(&self) -> Result<AwsCredentials, CredentialsError> {
+        let creds = self.inner.credentials().await?;
+        *self.expiration.lock().unwrap() = *creds.expires_at();
+        Ok(creds)
     }
 }
@@ -0,0 +1,94 @@
+use crate::{
+    client::sts::sts_client::{Credentials, Creds},
+    crypto::{self, EncryptionKey},
+    util::write_private_file,
+};
+use anyhow::Result;
+use log::info;
+use std::{fs, path::PathBuf};
+
+/// An encrypted file holding the session credentials for a token serial. The key is derived from the long-lived
+/// secret access key and the profile, token serial and STS endpoint are bound to the ciphertext, so a cache written
+/// for another user, device or endpoint is never used, as with the agent's sessions.
+pub struct SessionCache {
+    path: PathBuf,
+    key: EncryptionKey,
+    context: String,
+}
+
+impl SessionCache {
+    pub fn new(
+        path: PathBuf,
+        secret_access_key: &str,
+        profile: &str,
+        token_serial: &str,
+        sts_endpoint: Option<&str>,
+    ) -> SessionCache {
+        SessionCache {
+            path,
+            key: crypto::derive_key(secret_access_key, "aws-pass session cache"),
+            context: format!("{}\n{}\n{}", profile, token_serial, sts_endpoint.unwrap_or_default()),
+        }
+    }
+
+    /// Loads the cached credentials if present, readable and not yet expired.
+    pub fn load(&self) -> Option<Credentials> {
+        if !self.path.exists() {
+            return None;
+        }
+        match self.read() {
+            Ok(creds) if !creds.is_expired() => Some(creds),
+            Ok(_) => {
+                info!("Cached session expired");
+                None
+            }
+            Err(e) => {
+                info!("Ignoring unreadable session cache: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Stores the given **creds** in the cache.
+    pub fn store(&self, creds: &Credentials) -> Result<()> {
+        let data = crypto::encrypt(&self.key, creds.to_line().as_bytes(), self.context.as_bytes())?;
+        write_private_file(&self.path, &data)?;
+        Ok(())
+    }
+
+    fn read(&self) -> Result<Credentials> {
+        let data = fs::read(&self.path)?;
+        let line = crypto::decrypt(&self.key, &data, self.context.as_bytes())?;
+        Credentials::from_line(&String::from_utf8(line)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use tempfile::TempDir;
+
+    #[test]
+    fn loads_only_for_the_same_profile_serial_and_endpoint() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".session");
+        let cache = |profile, serial, endpoint| SessionCache::new(path.clone(), "secret", profile, serial, endpoint);
+        let creds = Credentials {
+            access_key_id: "id".to_string(),
+            secret_access_key: "key".to_string(),
+            session_token: "token".to_string(),
+            expiration: Utc::now() + Duration::hours(1),
+        };
+        cache("default", "serial", None).store(&creds).unwrap();
+        assert_eq!(cache("default", "serial", None).load().unwrap().session_token, "token");
+        assert!(cache("other", "serial", None).load().is_none());
+        assert!(cache("default", "other", None).load().is_none());
+        assert!(cache("default", "serial", Some("http://localhost:4566"))
+            .load()
+            .is_none());
+        assert!(SessionCache::new(path.clone(), "other", "default", "serial", None)
+            .load()
+            .is_none());
+    }
+}
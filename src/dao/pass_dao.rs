@@ -1,37 +1,73 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 
 #[async_trait]
 pub trait PassDao {
-    async fn create_password(&self, name: &str, value: &str, tags: Option<&[Tag]>) -> Result<Password>;
-    async fn create_random_password(
+    async fn create_password(
         &self,
         name: &str,
-        exclude_chars: Option<&str>,
-        length: Option<&i64>,
+        value: &str,
+        description: Option<&str>,
         tags: Option<&[Tag]>,
+        kms_key_id: Option<&str>,
     ) -> Result<Password>;
+    /// Generates a random password with Secrets Manager, of **length** without the **exclude_chars** and the excluded
+    /// character classes, with a character of each included class when **require_each_included_type** is set.
+    async fn random_password(
+        &self,
+        exclude_chars: Option<&str>,
+        length: Option<&i64>,
+        excluded_classes: ExcludedClasses,
+        require_each_included_type: bool,
+    ) -> Result<String>;
     async fn get_password(&self, id: &str) -> Result<Password>;
-    async fn get_password_by_name(&self, name: &str, filters: Option<&[Filter]>) -> Result<Password>;
+    async fn get_password_version(&self, id: &str, version_id: &str) -> Result<Password>;
+    /// Lists the versions of the password for the given **id**, newest first.
+    async fn list_password_versions(&self, id: &str) -> Result<Vec<PasswordVersion>>;
+    /// Makes the version **version_id** the current version of the password for the given **id**.
+    async fn set_current_version(&self, id: &str, version_id: &str) -> Result<()>;
     async fn update_password(&self, id: &str, value: &str) -> Result<()>;
-    async fn update_password_by_name(&self, name: &str, value: &str, filters: Option<&[Filter]>) -> Result<()>;
-    async fn delete_password(&self, id: &str) -> Result<()>;
-    async fn delete_password_by_name(&self, name: &str, filters: Option<&[Filter]>) -> Result<()>;
+    /// Deletes the password for the given **id** after a recovery window of **recovery_days**, or without one when
+    /// **force** is set. Returns the date the password will be deleted.
+    async fn delete_password(&self, id: &str, recovery_days: Option<i64>, force: bool)
+        -> Result<Option<DateTime<Utc>>>;
+    /// Cancels the deletion of the password for the given **id**.
+    async fn restore_password(&self, id: &str) -> Result<()>;
+    /// Describes the password for the given **id**, also while it's pending deletion.
+    async fn describe_password(&self, id: &str) -> Result<PasswordDetails>;
     async fn list_passwords(&self, filters: &[Filter]) -> Result<Vec<PasswordDetails>>;
 }
 
 pub type Tag = (String, String);
 pub type Filter = (String, Vec<String>);
+/// Whether lowercase letters, uppercase letters, numbers and punctuation are left out of a random password.
+pub type ExcludedClasses = (bool, bool, bool, bool);
 
 #[derive(Debug)]
 pub struct PasswordDetails {
     pub id: String,
     pub name: String,
+    pub tags: Vec<Tag>,
+    pub description: Option<String>,
+    pub kms_key_id: Option<String>,
+    /// When the password was removed, set while it's pending deletion.
+    pub deleted: Option<DateTime<Utc>>,
+    pub created: Option<DateTime<Utc>>,
+    pub last_changed: Option<DateTime<Utc>>,
+    pub last_accessed: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug)]
 pub struct Password {
     pub id: String,
-    pub name: String,
     pub value: String,
 }
+
+#[derive(Debug)]
+pub struct PasswordVersion {
+    pub id: String,
+    /// The stages labelling the version, `AWSCURRENT` for the current version and none for deprecated ones.
+    pub stages: Vec<String>,
+    pub created: Option<DateTime<Utc>>,
+}
@@ -1,8 +1,16 @@
-use super::pass_dao::{Filter, PassDao, Password, PasswordDetails, Tag};
-use crate::client::sm::{default_sm_client::DefaultSmClient, sm_client::SmClient};
+use super::pass_dao::{ExcludedClasses, Filter, PassDao, Password, PasswordDetails, PasswordVersion, Tag};
+use crate::client::sm::{
+    default_sm_client::DefaultSmClient,
+    sm_client::{SecretDetails, SmClient},
+};
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
 use rusoto_core::{credential::ProvideAwsCredentials, Region};
+use std::cmp::Reverse;
+
+/// The stage Secrets Manager labels a secret's current version with.
+pub const CURRENT_STAGE: &str = "AWSCURRENT";
 
 pub struct SmPassDao {
     sm_client: Box<dyn SmClient + Send + Sync>,
@@ -21,74 +29,100 @@ impl SmPassDao {
 
 #[async_trait]
 impl PassDao for SmPassDao {
-    async fn create_password(&self, name: &str, value: &str, tags: Option<&[Tag]>) -> Result<Password> {
-        let id = self.sm_client.create_secret_string(name, value, tags).await?;
+    async fn create_password(
+        &self,
+        name: &str,
+        value: &str,
+        description: Option<&str>,
+        tags: Option<&[Tag]>,
+        kms_key_id: Option<&str>,
+    ) -> Result<Password> {
+        let id = self
+            .sm_client
+            .create_secret_string(name, value, description, tags, kms_key_id)
+            .await?;
         Ok(Password {
             id,
-            name: name.to_string(),
             value: value.to_string(),
         })
     }
 
-    async fn create_random_password(
+    async fn random_password(
         &self,
-        name: &str,
         exclude_chars: Option<&str>,
         length: Option<&i64>,
-        tags: Option<&[Tag]>,
-    ) -> Result<Password> {
-        let value = self.sm_client.get_random_password(exclude_chars, length).await?;
-        self.create_password(name, &value, tags).await
+        excluded_classes: ExcludedClasses,
+        require_each_included_type: bool,
+    ) -> Result<String> {
+        self.sm_client
+            .get_random_password(exclude_chars, length, excluded_classes, require_each_included_type)
+            .await
     }
 
     async fn get_password(&self, id: &str) -> Result<Password> {
-        self.sm_client.get_secret_string(id).await.map(|s| {
-            Ok(Password {
+        self.sm_client.get_secret_string(id, None).await.map(|s| Password {
+            id: s.arn,
+            value: s.value,
+        })
+    }
+
+    async fn get_password_version(&self, id: &str, version_id: &str) -> Result<Password> {
+        self.sm_client
+            .get_secret_string(id, Some(version_id))
+            .await
+            .map(|s| Password {
                 id: s.arn,
-                name: s.name,
                 value: s.value,
             })
-        })?
     }
 
-    async fn get_password_by_name(&self, name: &str, filters: Option<&[Filter]>) -> Result<Password> {
-        let all_filters: &[Filter] = &[
-            &vec![("name".to_string(), vec![name.to_string()])],
-            filters.unwrap_or_default(),
-        ]
-        .concat();
-        let secrets = self.list_passwords(all_filters).await?;
-        if (secrets.len() > 1) {
-            // We update and delete secrets by name, we must enforce that get by name returns only 1 secret
-            panic!("Received more than 1 secret when listing by name");
-        }
-        Ok(self
+    async fn list_password_versions(&self, id: &str) -> Result<Vec<PasswordVersion>> {
+        let mut versions: Vec<PasswordVersion> = self
             .sm_client
-            .get_secret_string(&secrets.iter().next().unwrap().id)
-            .await?)
-        .map(|s| Password {
-            id: s.arn,
-            name: s.name,
-            value: s.value,
-        })
+            .list_secret_version_ids(id)
+            .await?
+            .into_iter()
+            .map(|v| PasswordVersion {
+                id: v.version_id,
+                stages: v.stages,
+                created: v.created_date.map(to_date),
+            })
+            .collect();
+        versions.sort_by_key(|v| Reverse(v.created));
+        Ok(versions)
+    }
+
+    async fn set_current_version(&self, id: &str, version_id: &str) -> Result<()> {
+        let versions = self.list_password_versions(id).await?;
+        let current = versions
+            .iter()
+            .find(|v| v.stages.iter().any(|s| s == CURRENT_STAGE))
+            .map(|v| v.id.as_str());
+        self.sm_client
+            .update_secret_version_stage(id, CURRENT_STAGE, version_id, current)
+            .await
     }
 
     async fn update_password(&self, id: &str, value: &str) -> Result<()> {
         self.sm_client.put_secret_string(id, value).await
     }
 
-    async fn update_password_by_name(&self, name: &str, value: &str, filters: Option<&[Filter]>) -> Result<()> {
-        let password = self.get_password_by_name(name, filters).await?;
-        self.update_password(&password.id, value).await
+    async fn delete_password(
+        &self,
+        id: &str,
+        recovery_days: Option<i64>,
+        force: bool,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let deletion_date = self.sm_client.delete_secret(id, recovery_days, force).await?;
+        Ok(deletion_date.map(to_date))
     }
 
-    async fn delete_password(&self, id: &str) -> Result<()> {
-        self.sm_client.delete_secret(id).await
+    async fn restore_password(&self, id: &str) -> Result<()> {
+        self.sm_client.restore_secret(id).await
     }
 
-    async fn delete_password_by_name(&self, name: &str, filters: Option<&[Filter]>) -> Result<()> {
-        let password = self.get_password_by_name(name, filters).await?;
-        self.delete_password(&password.id).await
+    async fn describe_password(&self, id: &str) -> Result<PasswordDetails> {
+        self.sm_client.describe_secret(id).await.map(to_password_details)
     }
 
     async fn list_passwords(&self, filters: &[Filter]) -> Result<Vec<PasswordDetails>> {
@@ -96,18 +130,11 @@ impl PassDao for SmPassDao {
         // Couldn't get Option<&str> to work
         let mut next_token: Option<String> = None;
         loop {
-            // TODO: Use better error handling
             let (ss, nt) = self
                 .sm_client
                 .list_secrets(Some(filters), next_token.as_deref())
                 .await?;
-            let pds = &mut ss
-                .into_iter()
-                .map(|s| PasswordDetails {
-                    id: s.arn,
-                    name: s.name,
-                })
-                .collect();
+            let pds = &mut ss.into_iter().map(to_password_details).collect();
             vec.append(pds);
             next_token = nt;
             if next_token.is_none() {
@@ -117,3 +144,22 @@ impl PassDao for SmPassDao {
         Ok(vec)
     }
 }
+
+fn to_password_details(s: SecretDetails) -> PasswordDetails {
+    PasswordDetails {
+        id: s.arn,
+        name: s.name,
+        tags: s.tags,
+        description: s.description,
+        kms_key_id: s.kms_key_id,
+        deleted: s.deleted_date.map(to_date),
+        created: s.created_date.map(to_date),
+        last_changed: s.last_changed_date.map(to_date),
+        last_accessed: s.last_accessed_date.map(to_date),
+    }
+}
+
+/// Converts a date in seconds since the epoch, as Secrets Manager gives them.
+fn to_date(seconds: f64) -> DateTime<Utc> {
+    Utc.timestamp(seconds.trunc() as i64, (seconds.fract() * 1e9) as u32)
+}
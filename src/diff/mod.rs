@@ -0,0 +1,41 @@
+/// A line of a diff between two texts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    /// A line of both texts, by its index in the new text.
+    Same(usize),
+    /// A line only the old text has, by its index in the old text.
+    Removed(usize),
+    /// A line only the new text has, by its index in the new text.
+    Added(usize),
+}
+
+/// Diffs the lines **old** and **new** by their longest common subsequence, removals of a run of lines come before the
+/// additions replacing them.
+pub fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Change> {
+    // lcs[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = match old[i] == new[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut changes = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            changes.push(Change::Same(j));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            changes.push(Change::Removed(i));
+            i += 1;
+        } else {
+            changes.push(Change::Added(j));
+            j += 1;
+        }
+    }
+    changes
+}
@@ -0,0 +1,351 @@
+use crate::error::AwsPassError;
+use anyhow::Result;
+use serde_json::{Map, Value};
+
+/// Stands in for values hidden by **diff_lines**.
+const MASK: &str = "********";
+
+/// A password entry. Entries follow either the `pass` conventions, where the first line is the password and later
+/// `key: value` lines are metadata such as a username or url, or the Secrets Manager convention of a JSON object such
+/// as `{"username": "admin", "password": "..."}`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entry {
+    /// The entry's lines, each with its line ending so that the entry is stored back as it was read.
+    Text(Vec<String>),
+    Json(Map<String, Value>),
+}
+
+impl Entry {
+    /// Parses the given stored **value** into an entry, values holding a JSON object are JSON entries.
+    pub fn parse(value: &str) -> Entry {
+        if value.trim_start().starts_with('{') {
+            if let Ok(Value::Object(map)) = serde_json::from_str(value) {
+                return Entry::Json(map);
+            }
+        }
+        Entry::Text(value.split_inclusive('\n').map(String::from).collect())
+    }
+
+    /// Builds a JSON entry from the given **fields**.
+    pub fn from_fields(fields: &[(String, String)]) -> Entry {
+        Entry::Json(
+            fields
+                .iter()
+                .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+                .collect(),
+        )
+    }
+
+    /// Parses a JSON entry edited as YAML, which must be a mapping.
+    pub fn from_yaml(yaml: &str) -> Result<Entry> {
+        match serde_yaml::from_str(yaml) {
+            Ok(Value::Object(map)) => Ok(Entry::Json(map)),
+            Ok(_) => Err(AwsPassError::InvalidInput("Entry must be a mapping of fields".to_string()).into()),
+            Err(e) => Err(AwsPassError::InvalidInput(format!("Entry is not valid YAML: {}", e)).into()),
+        }
+    }
+
+    /// The entry as YAML for editing, JSON entries only.
+    pub fn to_yaml(&self) -> Option<String> {
+        match self {
+            Entry::Text(_) => None,
+            Entry::Json(map) => serde_yaml::to_string(map).ok(),
+        }
+    }
+
+    /// The entry as it's stored.
+    pub fn to_value(&self) -> String {
+        match self {
+            Entry::Text(lines) => lines.concat(),
+            Entry::Json(map) => Value::Object(map.clone()).to_string(),
+        }
+    }
+
+    /// The password, the first line of a text entry or the `password` field of a JSON entry.
+    pub fn password(&self) -> String {
+        match self {
+            Entry::Text(lines) => lines.first().map_or(String::new(), |l| content(l).to_string()),
+            Entry::Json(_) => self.field("password").unwrap_or_default(),
+        }
+    }
+
+    /// The entry with its password replaced by **password**, the first line of a text entry or the `password` field
+    /// of a JSON entry, which is added when missing. The rest of the entry is kept.
+    pub fn with_password(&self, password: &str) -> Entry {
+        match self {
+            Entry::Text(lines) if lines.is_empty() => Entry::Text(vec![password.to_string()]),
+            Entry::Text(lines) => {
+                let mut lines = lines.clone();
+                lines[0] = with_content(&lines[0], password);
+                Entry::Text(lines)
+            }
+            Entry::Json(map) => {
+                let mut map = map.clone();
+                let key = map
+                    .keys()
+                    .find(|k| k.eq_ignore_ascii_case("password"))
+                    .cloned()
+                    .unwrap_or_else(|| "password".to_string());
+                map.insert(key, Value::String(password.to_string()));
+                Entry::Json(map)
+            }
+        }
+    }
+
+    /// Whether the entry is a plain password, a text entry of a single line.
+    pub fn is_plain(&self) -> bool {
+        matches!(self, Entry::Text(lines) if lines.len() <= 1)
+    }
+
+    /// The entry's **n**th line, starting at 1, text entries only.
+    pub fn line(&self, n: usize) -> Option<String> {
+        match self {
+            Entry::Text(lines) => n
+                .checked_sub(1)
+                .and_then(|i| lines.get(i))
+                .map(|l| content(l).to_string()),
+            Entry::Json(_) => None,
+        }
+    }
+
+    /// The metadata fields in the order they appear. Non-string JSON values are given as JSON.
+    pub fn fields(&self) -> Vec<(String, String)> {
+        match self {
+            Entry::Text(lines) => lines
+                .iter()
+                .skip(1)
+                .filter_map(|l| parse_field(content(l)))
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            Entry::Json(map) => map.iter().map(|(k, v)| (k.clone(), json_field_value(v))).collect(),
+        }
+    }
+
+    /// The value of the first metadata field named **key**, matched case-insensitively. For text entries `password`
+    /// is the first line unless the entry has a field by that name.
+    pub fn field(&self, key: &str) -> Option<String> {
+        if let Entry::Json(map) = self {
+            if let Some(value) = map.get(key) {
+                return Some(json_field_value(value));
+            }
+        }
+        self.fields()
+            .into_iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+            .or_else(|| match self {
+                Entry::Text(lines) if key.eq_ignore_ascii_case("password") => {
+                    lines.first().map(|l| content(l).to_string())
+                }
+                _ => None,
+            })
+    }
+
+    /// The `otpauth://` URI held by the entry, a line of a text entry or a string field of a JSON entry.
+    pub fn otp_uri(&self) -> Option<String> {
+        match self {
+            Entry::Text(lines) => lines
+                .iter()
+                .map(|l| content(l))
+                .find(|l| is_otp_uri(l))
+                .map(String::from),
+            Entry::Json(map) => map
+                .values()
+                .find_map(|v| v.as_str().filter(|s| is_otp_uri(s)).map(String::from)),
+        }
+    }
+
+    /// The entry with its `otpauth://` URI replaced by **uri**, or added when it holds none. Text entries gain it as a
+    /// line after the password and JSON entries as an `otpauth` field.
+    pub fn with_otp_uri(&self, uri: &str) -> Entry {
+        match self {
+            Entry::Text(lines) => {
+                let mut lines = lines.clone();
+                match lines.iter().position(|l| is_otp_uri(content(l))) {
+                    Some(i) => lines[i] = with_content(&lines[i], uri),
+                    None => match lines.first_mut() {
+                        None => lines.push(uri.to_string()),
+                        // A single line without an ending gains one, the URI then ends the entry as the line did
+                        Some(first) if ending(first).is_empty() => {
+                            first.push('\n');
+                            lines.push(uri.to_string());
+                        }
+                        Some(first) => {
+                            let line = format!("{}{}", uri, ending(first));
+                            lines.insert(1, line);
+                        }
+                    },
+                }
+                Entry::Text(lines)
+            }
+            Entry::Json(map) => {
+                let mut map = map.clone();
+                let key = map
+                    .iter()
+                    .find(|(_, v)| v.as_str().is_some_and(is_otp_uri))
+                    .map_or("otpauth".to_string(), |(k, _)| k.clone());
+                map.insert(key, Value::String(uri.to_string()));
+                Entry::Json(map)
+            }
+        }
+    }
+
+    /// The entry's lines for comparing versions, each with a masked form hiding its value. JSON entries give a
+    /// `key: value` line per field. Field lines keep their key when masked, other lines are masked whole.
+    pub fn diff_lines(&self) -> Vec<(String, String)> {
+        let mask_field = |key: &str| format!("{}: {}", key, MASK);
+        match self {
+            Entry::Text(lines) => lines
+                .iter()
+                .map(|l| content(l))
+                .enumerate()
+                .map(|(i, line)| {
+                    let masked = match parse_field(line) {
+                        Some((key, _)) if i > 0 => mask_field(key),
+                        _ => MASK.to_string(),
+                    };
+                    (line.to_string(), masked)
+                })
+                .collect(),
+            Entry::Json(_) => self
+                .fields()
+                .into_iter()
+                .map(|(key, value)| (format!("{}: {}", key, value), mask_field(&key)))
+                .collect(),
+        }
+    }
+
+    /// The entry as a JSON object with its `password`, its `fields` and, for text entries, its `notes`.
+    pub fn to_json(&self) -> Value {
+        let fields = match self {
+            Entry::Text(_) => self.fields().into_iter().map(|(k, v)| (k, Value::String(v))).collect(),
+            Entry::Json(map) => map.clone(),
+        };
+        let mut json = Map::new();
+        json.insert("password".to_string(), Value::String(self.password()));
+        json.insert("fields".to_string(), Value::Object(fields));
+        if let Entry::Text(_) = self {
+            json.insert(
+                "notes".to_string(),
+                self.notes().into_iter().map(Value::String).collect(),
+            );
+        }
+        Value::Object(json)
+    }
+
+    /// The lines after the password that aren't metadata fields, text entries only.
+    pub fn notes(&self) -> Vec<String> {
+        match self {
+            Entry::Text(lines) => lines
+                .iter()
+                .skip(1)
+                .map(|l| content(l))
+                .filter(|l| parse_field(l).is_none())
+                .map(String::from)
+                .collect(),
+            Entry::Json(_) => Vec::new(),
+        }
+    }
+}
+
+/// The **line** without its line ending.
+fn content(line: &str) -> &str {
+    line.strip_suffix('\n')
+        .map_or(line, |l| l.strip_suffix('\r').unwrap_or(l))
+}
+
+/// The **line**'s line ending, empty for a last line without one.
+fn ending(line: &str) -> &str {
+    &line[content(line).len()..]
+}
+
+/// The **line** with its content replaced by **content**, keeping its line ending.
+fn with_content(line: &str, content: &str) -> String {
+    format!("{}{}", content, ending(line))
+}
+
+fn is_otp_uri(s: &str) -> bool {
+    s.trim_start().starts_with("otpauth://")
+}
+
+fn json_field_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+/// Parses a `key: value` line. The colon must be followed by a space or end the line so that urls such as
+/// `otpauth://...` aren't mistaken for fields.
+fn parse_field(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once(':')?;
+    let key = key.trim();
+    if key.is_empty() || !(value.is_empty() || value.starts_with(' ')) {
+        return None;
+    }
+    Some((key, value.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URI: &str = "otpauth://hotp/Example:alice?secret=MZXW6YTB&counter=1";
+    const NEXT_URI: &str = "otpauth://hotp/Example:alice?secret=MZXW6YTB&counter=2";
+
+    #[test]
+    fn keeps_line_endings() {
+        for value in [
+            "secret",
+            "secret\n",
+            "secret\nuser: alice\n",
+            "secret\r\nuser: alice\r\nnote\r\n",
+            "a\n\nb",
+        ] {
+            assert_eq!(Entry::parse(value).to_value(), value);
+        }
+    }
+
+    #[test]
+    fn reads_lines_without_their_endings() {
+        let entry = Entry::parse("secret\r\nuser: alice\r\nnote\r\n");
+        assert_eq!(entry.password(), "secret");
+        assert_eq!(entry.line(3).as_deref(), Some("note"));
+        assert_eq!(entry.field("user").as_deref(), Some("alice"));
+        assert_eq!(entry.notes(), vec!["note"]);
+    }
+
+    #[test]
+    fn replaces_otp_uri_in_place() {
+        let value = format!("secret\r\n{}\r\nuser: alice\r\n", URI);
+        let entry = Entry::parse(&value);
+        assert_eq!(entry.otp_uri().as_deref(), Some(URI));
+        assert_eq!(
+            entry.with_otp_uri(NEXT_URI).to_value(),
+            format!("secret\r\n{}\r\nuser: alice\r\n", NEXT_URI)
+        );
+    }
+
+    #[test]
+    fn adds_otp_uri_after_password() {
+        let add = |value: &str| Entry::parse(value).with_otp_uri(URI).to_value();
+        assert_eq!(add(""), URI);
+        assert_eq!(add("secret"), format!("secret\n{}", URI));
+        assert_eq!(add("secret\n"), format!("secret\n{}\n", URI));
+        assert_eq!(add("secret\r\nnote"), format!("secret\r\n{}\r\nnote", URI));
+    }
+
+    #[test]
+    fn replaces_password_keeping_rest() {
+        let replace = |value: &str| Entry::parse(value).with_password("new").to_value();
+        assert_eq!(replace(""), "new");
+        assert_eq!(replace("old"), "new");
+        assert_eq!(replace("old\r\nuser: alice\r\n"), "new\r\nuser: alice\r\n");
+        assert_eq!(
+            Entry::parse(r#"{"Password":"old","user":"alice"}"#)
+                .with_password("new")
+                .to_value(),
+            r#"{"Password":"new","user":"alice"}"#
+        );
+    }
+}
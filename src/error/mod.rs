@@ -0,0 +1,113 @@
+use rusoto_core::RusotoError;
+use std::{error::Error, fmt::Debug, io};
+use thiserror::Error;
+
+const MFA_FAILED: &str = "MFA authentication failed or expired: ";
+
+#[derive(Debug, Error)]
+pub enum AwsPassError {
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error("{0} matches {1} passwords")]
+    Ambiguous(String, usize),
+    #[error("Access denied: {0}")]
+    AccessDenied(String),
+    #[error("MFA authentication failed or expired: {0}")]
+    MfaFailed(String),
+    #[error("Throttled by AWS: {0}")]
+    Throttled(String),
+    #[error("{0} already exists")]
+    AlreadyExists(String),
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl AwsPassError {
+    /// The variant's name, for structured errors.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AwsPassError::NotFound(_) => "NotFound",
+            AwsPassError::Ambiguous(..) => "Ambiguous",
+            AwsPassError::AccessDenied(_) => "AccessDenied",
+            AwsPassError::MfaFailed(_) => "MfaFailed",
+            AwsPassError::Throttled(_) => "Throttled",
+            AwsPassError::AlreadyExists(_) => "AlreadyExists",
+            AwsPassError::InvalidInput(_) => "InvalidInput",
+            AwsPassError::Io(_) => "Io",
+        }
+    }
+
+    /// The process exit code for the error, distinct per variant so that scripts can tell them apart.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AwsPassError::NotFound(_) => 2,
+            AwsPassError::Ambiguous(..) => 3,
+            AwsPassError::AccessDenied(_) => 4,
+            AwsPassError::MfaFailed(_) => 5,
+            AwsPassError::Throttled(_) => 6,
+            AwsPassError::AlreadyExists(_) => 7,
+            AwsPassError::InvalidInput(_) => 8,
+            AwsPassError::Io(_) => 9,
+        }
+    }
+}
+
+/// The kind and exit code of **err** when it's an `AwsPassError`, or an I/O error that `?` passed along as is.
+pub fn kind_and_exit_code(err: &anyhow::Error) -> Option<(&'static str, i32)> {
+    if let Some(e) = err.downcast_ref::<AwsPassError>() {
+        return Some((e.kind(), e.exit_code()));
+    }
+    err.chain().any(|e| e.is::<io::Error>()).then_some(("Io", 9))
+}
+
+/// Translates a rusoto **err** for a request about the given **subject** into an `AwsPassError` where it has a
+/// meaningful counterpart. Service errors are matched on their variant name, which rusoto keeps consistent across
+/// operations.
+pub fn from_rusoto<E>(err: RusotoError<E>, subject: &str) -> anyhow::Error
+where
+    E: Error + Debug + Send + Sync + 'static,
+{
+    match err {
+        RusotoError::Service(e) => {
+            let debug = format!("{:?}", e);
+            match debug.split('(').next().unwrap_or_default() {
+                "ResourceNotFound" => AwsPassError::NotFound(subject.to_string()).into(),
+                "ResourceExists" => AwsPassError::AlreadyExists(subject.to_string()).into(),
+                "InvalidRequest" if e.to_string().contains("scheduled for deletion") => {
+                    AwsPassError::InvalidInput(format!(
+                        "{} was removed and is pending deletion, restore it with `aws-pass restore` or remove it for \
+                        good with `aws-pass remove --force`",
+                        subject
+                    ))
+                    .into()
+                }
+                "InvalidParameter" | "InvalidRequest" => AwsPassError::InvalidInput(e.to_string()).into(),
+                _ => e.into(),
+            }
+        }
+        // Credential providers can only pass messages along, MFA failures are recognized by their message
+        RusotoError::Credentials(e) => match e.message.strip_prefix(MFA_FAILED) {
+            Some(message) => AwsPassError::MfaFailed(message.to_string()).into(),
+            None => anyhow::anyhow!("Could not obtain credentials: {}", e.message),
+        },
+        RusotoError::Validation(message) => AwsPassError::InvalidInput(message).into(),
+        RusotoError::Unknown(response) => {
+            let body = response.body_as_str().to_string();
+            if body.contains("Throttling") {
+                AwsPassError::Throttled(body).into()
+            } else if body.contains("AccessDenied") {
+                AwsPassError::AccessDenied(body).into()
+            } else if body.contains("ExpiredToken")
+                || body.contains("UnrecognizedClient")
+                || body.contains("InvalidClientTokenId")
+            {
+                AwsPassError::MfaFailed(body).into()
+            } else {
+                anyhow::anyhow!("Unexpected {} response: {}", response.status, body)
+            }
+        }
+        e => e.into(),
+    }
+}
@@ -0,0 +1,42 @@
+const MATCH: i64 = 1;
+const CONSECUTIVE_BONUS: i64 = 4;
+const WORD_START_BONUS: i64 = 6;
+const GAP_PENALTY: i64 = 1;
+
+/// Scores how well **text** matches **pattern**, none when the pattern's characters don't all appear in the text in
+/// order. Matching ignores case, runs of consecutive characters and characters starting a word or a folder score
+/// higher, gaps between matched characters score lower.
+pub fn score(pattern: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut last: Option<usize> = None;
+    let mut from = 0;
+    for p in pattern.chars().flat_map(char::to_lowercase) {
+        let i = from + text[from..].iter().position(|&c| c == p)?;
+        score += MATCH;
+        if i == 0 || matches!(text[i - 1], '/' | '-' | '_' | '.' | ' ' | '@') {
+            score += WORD_START_BONUS;
+        }
+        match last {
+            Some(l) if l + 1 == i => score += CONSECUTIVE_BONUS,
+            Some(l) => score -= GAP_PENALTY * (i - l - 1) as i64,
+            None => {}
+        }
+        last = Some(i);
+        from = i + 1;
+    }
+    Some(score)
+}
+
+/// The **items** whose keys match **pattern**, best match first then by key.
+pub fn rank<T, F>(pattern: &str, items: Vec<T>, key: F) -> Vec<T>
+where
+    F: Fn(&T) -> &str,
+{
+    let mut scored: Vec<(i64, T)> = items
+        .into_iter()
+        .filter_map(|item| score(pattern, key(&item)).map(|s| (s, item)))
+        .collect();
+    scored.sort_by(|(a, x), (b, y)| b.cmp(a).then_with(|| key(x).cmp(key(y))));
+    scored.into_iter().map(|(_, item)| item).collect()
+}
@@ -0,0 +1,297 @@
+use crate::{error::AwsPassError, settings::config::PolicyConfig};
+use anyhow::Result;
+use rand::{rngs::OsRng, seq::SliceRandom, Rng};
+use std::str::FromStr;
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+/// The punctuation `GetRandomPassword` draws from.
+const SYMBOLS: &str = "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+/// Characters that are easily mistaken for one another.
+pub const AMBIGUOUS: &str = "0O1lI|`'\"";
+/// The words passphrases are drawn from when no wordlist is given, the BIP39 English wordlist.
+const WORDLIST: &str = include_str!("wordlist.txt");
+
+/// Where `generate` gets passwords from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    /// Secrets Manager's `GetRandomPassword`, which needs a session.
+    Aws,
+    /// The operating system's random number generator.
+    Local,
+}
+
+impl FromStr for Source {
+    type Err = AwsPassError;
+
+    fn from_str(s: &str) -> Result<Source, AwsPassError> {
+        match s {
+            "aws" => Ok(Source::Aws),
+            "local" => Ok(Source::Local),
+            _ => Err(AwsPassError::InvalidInput(format!(
+                "Unknown password source {}, expected aws or local",
+                s
+            ))),
+        }
+    }
+}
+
+/// What passwords look like, either characters of the included classes or, when **words** is set, a passphrase.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Policy {
+    pub length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    /// At least one character of each included class.
+    pub require_each_type: bool,
+    pub exclude_ambiguous: bool,
+    pub exclude_chars: String,
+    /// The number of words of a passphrase.
+    pub words: Option<usize>,
+    pub separator: String,
+}
+
+impl Default for Policy {
+    /// `GetRandomPassword`'s defaults.
+    fn default() -> Policy {
+        Policy {
+            length: 32,
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: true,
+            require_each_type: true,
+            exclude_ambiguous: false,
+            exclude_chars: String::new(),
+            words: None,
+            separator: "-".to_string(),
+        }
+    }
+}
+
+impl Policy {
+    /// Overrides the policy with what **config** sets.
+    pub fn apply(&mut self, config: &PolicyConfig) {
+        if let Some(length) = config.length {
+            self.length = length as usize;
+        }
+        for (class, value) in [
+            (&mut self.lowercase, config.lowercase),
+            (&mut self.uppercase, config.uppercase),
+            (&mut self.digits, config.digits),
+            (&mut self.symbols, config.symbols),
+            (&mut self.require_each_type, config.require_each_type),
+            (&mut self.exclude_ambiguous, config.exclude_ambiguous),
+        ] {
+            if let Some(value) = value {
+                *class = value;
+            }
+        }
+        if let Some(exclude_chars) = &config.exclude_chars {
+            self.exclude_chars = exclude_chars.clone();
+        }
+        if let Some(words) = config.words {
+            self.words = Some(words as usize);
+        }
+        if let Some(separator) = &config.separator {
+            self.separator = separator.clone();
+        }
+    }
+
+    /// The characters excluded, including the ambiguous ones when they're excluded.
+    pub fn excluded(&self) -> String {
+        match self.exclude_ambiguous {
+            true => format!("{}{}", self.exclude_chars, AMBIGUOUS),
+            false => self.exclude_chars.clone(),
+        }
+    }
+
+    /// Generates a password, or a passphrase of words from **wordlist** or from the built-in list.
+    pub fn generate(&self, wordlist: Option<&str>) -> Result<String> {
+        match self.words {
+            Some(words) => passphrase(words, &self.separator, wordlist.unwrap_or(WORDLIST)),
+            None => self.password(),
+        }
+    }
+
+    fn password(&self) -> Result<String> {
+        let excluded = self.excluded();
+        let classes: Vec<Vec<char>> = [
+            (self.lowercase, LOWERCASE),
+            (self.uppercase, UPPERCASE),
+            (self.digits, DIGITS),
+            (self.symbols, SYMBOLS),
+        ]
+        .iter()
+        .filter(|(included, _)| *included)
+        .map(|(_, chars)| chars.chars().filter(|c| !excluded.contains(*c)).collect::<Vec<char>>())
+        .filter(|chars| !chars.is_empty())
+        .collect();
+        let all: Vec<char> = classes.concat();
+        if all.is_empty() {
+            return Err(AwsPassError::InvalidInput("The policy excludes every character".to_string()).into());
+        }
+        if self.length == 0 || (self.require_each_type && self.length < classes.len()) {
+            return Err(
+                AwsPassError::InvalidInput(format!("A length of {} is too short for the policy", self.length)).into(),
+            );
+        }
+        let mut rng = OsRng;
+        let mut password: Vec<char> = Vec::with_capacity(self.length);
+        if self.require_each_type {
+            password.extend(classes.iter().map(|chars| chars[rng.gen_range(0..chars.len())]));
+        }
+        while password.len() < self.length {
+            password.push(all[rng.gen_range(0..all.len())]);
+        }
+        password.shuffle(&mut rng);
+        Ok(password.into_iter().collect())
+    }
+}
+
+/// Draws **words** words from **wordlist** joined by **separator**. The wordlist holds a word per line, optionally
+/// after its dice roll as in the EFF and Diceware lists.
+fn passphrase(words: usize, separator: &str, wordlist: &str) -> Result<String> {
+    let list: Vec<&str> = wordlist
+        .lines()
+        .filter_map(|line| line.split_whitespace().last())
+        .collect();
+    if list.len() < 2 {
+        return Err(AwsPassError::InvalidInput("The wordlist holds fewer than 2 words".to_string()).into());
+    }
+    if words == 0 {
+        return Err(AwsPassError::InvalidInput("A passphrase needs at least 1 word".to_string()).into());
+    }
+    let mut rng = OsRng;
+    let chosen: Vec<&str> = (0..words).map(|_| list[rng.gen_range(0..list.len())]).collect();
+    Ok(chosen.join(separator))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(length: usize) -> Policy {
+        Policy {
+            length,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn generates_from_included_classes() {
+        let password = policy(4096).generate(None).unwrap();
+        assert_eq!(password.chars().count(), 4096);
+        for class in [LOWERCASE, UPPERCASE, DIGITS, SYMBOLS] {
+            assert!(password.chars().any(|c| class.contains(c)), "{}", class);
+        }
+        let letters = Policy {
+            digits: false,
+            symbols: false,
+            ..policy(4096)
+        };
+        assert!(letters.generate(None).unwrap().chars().all(|c| c.is_ascii_alphabetic()));
+    }
+
+    #[test]
+    fn places_a_character_of_each_class() {
+        // At the shortest length the policy allows, each class still shows up
+        for _ in 0..200 {
+            let password = policy(4).generate(None).unwrap();
+            for class in [LOWERCASE, UPPERCASE, DIGITS, SYMBOLS] {
+                assert!(password.chars().any(|c| class.contains(c)), "{} in {}", class, password);
+            }
+        }
+    }
+
+    #[test]
+    fn leaves_out_excluded_characters() {
+        let excluding = Policy {
+            exclude_ambiguous: true,
+            exclude_chars: "abc$".to_string(),
+            ..policy(4096)
+        };
+        assert_eq!(excluding.excluded(), format!("abc${}", AMBIGUOUS));
+        let password = excluding.generate(None).unwrap();
+        assert!(!password.chars().any(|c| excluding.excluded().contains(c)));
+    }
+
+    #[test]
+    fn rejects_impossible_policies() {
+        let too_short = Policy {
+            require_each_type: true,
+            ..policy(3)
+        };
+        assert!(too_short.generate(None).is_err());
+        assert!(Policy {
+            require_each_type: false,
+            ..too_short
+        }
+        .generate(None)
+        .is_ok());
+        assert!(policy(0).generate(None).is_err());
+        let nothing = Policy {
+            lowercase: false,
+            uppercase: false,
+            symbols: false,
+            exclude_chars: DIGITS.to_string(),
+            ..policy(8)
+        };
+        assert!(nothing.generate(None).is_err());
+    }
+
+    #[test]
+    fn applies_what_config_sets() {
+        let mut policy = Policy::default();
+        policy.apply(&PolicyConfig {
+            length: Some(12),
+            symbols: Some(false),
+            exclude_chars: Some("xyz".to_string()),
+            ..Default::default()
+        });
+        policy.apply(&PolicyConfig {
+            words: Some(5),
+            separator: Some(" ".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(
+            policy,
+            Policy {
+                length: 12,
+                symbols: false,
+                exclude_chars: "xyz".to_string(),
+                words: Some(5),
+                separator: " ".to_string(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn draws_passphrases_from_the_wordlist() {
+        let words = Policy {
+            words: Some(6),
+            separator: "+".to_string(),
+            ..Default::default()
+        };
+        let passphrase = words.generate(None).unwrap();
+        let builtin: Vec<&str> = WORDLIST.lines().collect();
+        assert_eq!(builtin.len(), 2048);
+        assert_eq!(passphrase.split('+').count(), 6);
+        assert!(passphrase.split('+').all(|w| builtin.contains(&w)));
+        // Dice rolls of the EFF and Diceware lists are skipped
+        let eff = "11111\tabacus\n11112\tabdomen\n";
+        let passphrase = words.generate(Some(eff)).unwrap();
+        assert!(passphrase.split('+').all(|w| w == "abacus" || w == "abdomen"));
+    }
+
+    #[test]
+    fn rejects_bad_passphrases() {
+        assert!(passphrase(4, "-", "only").is_err());
+        assert!(passphrase(4, "-", "").is_err());
+        assert!(passphrase(0, "-", WORDLIST).is_err());
+    }
+}
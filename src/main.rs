@@ -1,33 +1,124 @@
-#[macro_use]
-mod macros;
+// Constructors return their trait as `impl Trait`, hiding the implementation behind it
+#![allow(clippy::new_ret_no_self)]
+mod agent;
 mod client;
+mod clipboard;
 mod creds;
+mod crypto;
 mod dao;
+mod diff;
+mod entry;
+mod error;
+mod fuzzy;
+mod generator;
+mod otp;
+mod output;
+mod picker;
+mod prompt;
+mod settings;
+mod shell;
 mod store;
+mod tree;
 mod util;
-use rusoto_core::Region;
-use std::{env, path};
-use store::default_pass_store::DefaultPassStore;
-use structopt::StructOpt;
+use anyhow::Result;
+use chrono::Utc;
+use error::AwsPassError;
+use generator::Source;
+use regex::RegexBuilder;
+use settings::{Settings, SettingsArgs};
+use shell::Shell;
+use std::{env, path, process};
+use store::{
+    default_pass_store::DefaultPassStore,
+    pass_store::{GenerateOptions, InsertMode, PassStore, RemoveOptions, Selector},
+};
+use structopt::{
+    clap::{AppSettings, ErrorKind},
+    StructOpt,
+};
 
 #[derive(Debug, StructOpt)]
 enum Command {
     /// Initializes the store.
     Init {},
-    /// Lists the passwords given an optional **prefix**.
+    /// Lists the passwords as a tree given an optional **prefix**, a folder's prefix shows its subtree.
     List {
         #[structopt(short, long)]
         prefix: Option<String>,
+        /// Lists one full name per line instead.
+        #[structopt(long, conflicts_with = "depth")]
+        flat: bool,
+        /// Only shows this many levels of folders.
+        #[structopt(short, long)]
+        depth: Option<usize>,
+    },
+    /// Finds the passwords whose names fuzzy match a **term**, or whose descriptions or tag values start with it.
+    Find { term: String },
+    /// Shows the passwords' lines matching a regex **pattern**, after confirming that their values are decrypted.
+    Grep {
+        pattern: String,
+        /// Only searches the passwords whose names start with the prefix.
+        #[structopt(short, long)]
+        prefix: Option<String>,
+        /// Matches regardless of case.
+        #[structopt(short, long)]
+        ignore_case: bool,
+        /// Searches without asking for confirmation.
+        #[structopt(short, long)]
+        yes: bool,
     },
-    /// Shows a password given a **name**.
+    /// Picks a password with a fuzzy finder, then shows, copies or edits it, its OTP code or its username.
+    Pick {
+        /// Lists the store again instead of using the listing cached for the session.
+        #[structopt(long)]
+        refresh: bool,
+        /// Seconds after which the clipboard is restored.
+        #[structopt(short, long, default_value = "45")]
+        timeout: u64,
+    },
+    /// Shows a password given a **name**, optionally copying it to the clipboard instead.
     Show {
         #[structopt(short, long)]
         name: String,
+        /// Only show the given line of the password, starting at 1.
+        #[structopt(long, conflicts_with = "field")]
+        line: Option<usize>,
+        /// Only show the given metadata field of the password, e.g. `username`.
+        #[structopt(short, long)]
+        field: Option<String>,
+        /// Copies the password, or the selected line or field, to the clipboard instead of printing it.
+        #[structopt(short, long)]
+        clip: bool,
+        /// Seconds after which the clipboard is restored.
+        #[structopt(short, long, default_value = "45")]
+        timeout: u64,
+        /// Shows an older version, given by a stage such as `AWSPREVIOUS` or a prefix of its id from `history`.
+        #[structopt(long)]
+        version: Option<String>,
     },
     /// Inserts a password given a **name**.
     Insert {
         #[structopt(short, long)]
         name: String,
+        /// Echoes the password as it's typed and only asks for it once.
+        #[structopt(short, long, conflicts_with = "stdin")]
+        echo: bool,
+        /// Reads the password from all of stdin, implied when stdin isn't a terminal.
+        #[structopt(long)]
+        stdin: bool,
+        /// Reads a multiline entry, echoing it, until end of input.
+        #[structopt(short, long, conflicts_with_all = &["echo", "stdin"])]
+        multiline: bool,
+        /// Inserts a JSON object with the given `key=value` field, repeatable. The value is asked for, hidden, when
+        /// only a `key` is given.
+        #[structopt(
+            short,
+            long = "field",
+            number_of_values = 1,
+            parse(from_str = parse_field_arg),
+            conflicts_with_all = &["echo", "stdin", "multiline"]
+        )]
+        fields: Vec<(String, Option<String>)>,
     },
     /// Edits a password given its **name**.
     Edit {
@@ -38,47 +129,578 @@ enum Command {
     Generate {
         #[structopt(short, long)]
         name: String,
+        /// Generates the password locally or with Secrets Manager's `GetRandomPassword`, `local` unless set in the
+        /// config.
+        #[structopt(long)]
+        source: Option<Source>,
+        /// A policy: `aws-console`, `pin6`, `passphrase` or one configured as `policies.<name>`.
+        #[structopt(short, long)]
+        policy: Option<String>,
         #[structopt(short, long)]
         exclude_chars: Option<String>,
         #[structopt(short, long)]
-        length: Option<i64>,
+        length: Option<usize>,
+        /// Leaves out lowercase letters.
+        #[structopt(long)]
+        no_lowercase: bool,
+        /// Leaves out uppercase letters.
+        #[structopt(long)]
+        no_uppercase: bool,
+        /// Leaves out digits.
+        #[structopt(long)]
+        no_digits: bool,
+        /// Leaves out punctuation.
+        #[structopt(long)]
+        no_symbols: bool,
+        /// Includes at least one character of each included class.
+        #[structopt(long)]
+        require_each_type: bool,
+        /// Leaves out characters that are easily mistaken for one another, such as `0`, `O`, `1` and `l`.
+        #[structopt(long)]
+        no_ambiguous: bool,
+        /// Generates a passphrase of this many words instead.
+        #[structopt(short, long)]
+        words: Option<usize>,
+        /// Separates the words of a passphrase, `-` by default.
+        #[structopt(long)]
+        separator: Option<String>,
+        /// A file of words for passphrases, one per line or after their dice rolls as in the EFF wordlists.
+        #[structopt(long, parse(from_os_str))]
+        wordlist: Option<path::PathBuf>,
+        /// Replaces the first line or `password` field of an existing password, keeping the rest as it is.
+        #[structopt(short, long)]
+        in_place: bool,
+        /// Overwrites an existing password that holds nothing else.
+        #[structopt(short, long, requires = "in-place")]
+        force: bool,
     },
-    /// Removes a password given its **name**.
+    /// Removes a password given its **name**, it can be restored until its recovery window ends.
     Remove {
         #[structopt(short, long)]
         name: String,
+        /// Removes **name** and every password under it as a folder, after confirmation.
+        #[structopt(short, long)]
+        recursive: bool,
+        /// Removes without asking for confirmation.
+        #[structopt(short, long)]
+        yes: bool,
+        /// Only shows the passwords that would be removed.
+        #[structopt(long)]
+        dry_run: bool,
+        /// Days, between 7 and 30, during which the password can be restored.
+        #[structopt(long, parse(try_from_str = parse_recovery_days), conflicts_with = "force")]
+        recovery_days: Option<i64>,
+        /// Deletes the password for good, after confirmation, so that its name can be reused right away.
+        #[structopt(long)]
+        force: bool,
+    },
+    /// Lists the removed passwords that can still be restored.
+    Trash {},
+    /// Restores a removed password given its **name**.
+    Restore {
+        #[structopt(short, long)]
+        name: String,
+    },
+    /// Moves a password **from** a name **to** another, or every password under a folder ending in `/`.
+    Mv {
+        from: String,
+        to: String,
+        /// Overwrites passwords that exist at the destination.
+        #[structopt(short, long)]
+        force: bool,
+    },
+    /// Copies a password **from** a name **to** another, or every password under a folder ending in `/`.
+    Cp {
+        from: String,
+        to: String,
+        /// Overwrites passwords that exist at the destination.
+        #[structopt(short, long)]
+        force: bool,
+        /// Copies into another configured store.
+        #[structopt(long)]
+        to_store: Option<String>,
+        /// Copies into another region, not with a custom endpoint.
+        #[structopt(long)]
+        to_region: Option<String>,
+    },
+    /// Lists the versions of a password given its **name**, newest first.
+    History {
+        #[structopt(short, long)]
+        name: String,
+    },
+    /// Shows the lines that differ between two versions of a password given its **name**, with values masked.
+    Diff {
+        #[structopt(short, long)]
+        name: String,
+        /// The older version, a stage or a prefix of its id.
+        from: String,
+        /// The newer version, a stage or a prefix of its id.
+        #[structopt(default_value = "AWSCURRENT")]
+        to: String,
+        /// Shows values instead of masking them.
+        #[structopt(short, long)]
+        unmask: bool,
+    },
+    /// Makes an older version the current version of a password given its **name**.
+    Rollback {
+        #[structopt(short, long)]
+        name: String,
+        /// The version, a stage or a prefix of its id.
+        version: String,
+    },
+    /// Restores the clipboard after a timeout, spawned by `show --clip`.
+    #[structopt(setting = AppSettings::Hidden)]
+    ClipRestore {
+        #[structopt(long)]
+        timeout: u64,
+    },
+    /// Generates one-time passwords from `otpauth://` URIs held by passwords.
+    Otp {
+        #[structopt(subcommand)]
+        cmd: OtpCommand,
+    },
+    /// Manages the seed of a virtual MFA device from which MFA token codes are computed instead of asked for.
+    MfaSeed {
+        #[structopt(subcommand)]
+        cmd: MfaSeedCommand,
+    },
+    /// Runs commands in an interactive session sharing one MFA session, with history and completion of names.
+    Shell {},
+    /// Manages the background agent holding the MFA session.
+    Agent {
+        #[structopt(subcommand)]
+        cmd: AgentCommand,
+    },
+    /// Manages the stores sharing the account.
+    Stores {
+        #[structopt(subcommand)]
+        cmd: StoresCommand,
+    },
+    /// Manages the store's `config.toml`.
+    Config {
+        #[structopt(subcommand)]
+        cmd: ConfigCommand,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum OtpCommand {
+    /// Shows the current one-time password given a **name**, advancing the counter of HOTP URIs.
+    Show {
+        #[structopt(short, long)]
+        name: String,
+        /// Copies the code to the clipboard instead of printing it.
+        #[structopt(short, long)]
+        clip: bool,
+        /// Seconds after which the clipboard is restored.
+        #[structopt(short, long, default_value = "45")]
+        timeout: u64,
+        /// Also prints the seconds before a TOTP code changes.
+        #[structopt(short, long)]
+        remaining: bool,
+    },
+    /// Adds an `otpauth://` URI read from stdin to the password given a **name**, creating it if needed.
+    Insert {
+        #[structopt(short, long)]
+        name: String,
+        /// Replaces the URI the password already holds.
+        #[structopt(short, long)]
+        force: bool,
+    },
+    /// Shows the `otpauth://` URI given a **name**.
+    Uri {
+        #[structopt(short, long)]
+        name: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum MfaSeedCommand {
+    /// Stores the seed, read from stdin as base32 or an `otpauth://` URI, encrypted in the store dir.
+    Set {},
+    /// Removes the stored seed, MFA token codes are asked for again.
+    Remove {},
+}
+
+#[derive(Debug, StructOpt)]
+enum AgentCommand {
+    /// Starts the agent in the background.
+    Start {
+        /// Seconds without a request after which the agent exits.
+        #[structopt(short, long, default_value = "3600")]
+        idle_timeout: u64,
+    },
+    /// Stops the agent.
+    Stop {},
+    /// Shows whether the agent is running and when its session expires.
+    Status {},
+    /// Runs the agent in the foreground.
+    Run {
+        /// Seconds without a request after which the agent exits.
+        #[structopt(short, long, default_value = "3600")]
+        idle_timeout: u64,
     },
 }
 
 #[derive(Debug, StructOpt)]
+enum ConfigCommand {
+    /// Shows the value of a **key**, e.g. `aws.region`.
+    Get { key: String },
+    /// Sets a **key** to a **value**.
+    Set { key: String, value: String },
+    /// Unsets a **key**, restoring its default.
+    Unset { key: String },
+    /// Lists the keys that are set with their values.
+    List {},
+}
+
+#[derive(Debug, StructOpt)]
+enum StoresCommand {
+    /// Lists the configured stores, marking the selected one.
+    List {},
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(global_settings = &[AppSettings::VersionlessSubcommands])]
 struct Opt {
+    /// Lifetime in seconds of the MFA session, between 900 and 129600, 900 unless set in the config.
+    #[structopt(
+        long,
+        global = true,
+        env = "AWS_PASS_SESSION_DURATION",
+        parse(try_from_str = parse_session_duration)
+    )]
+    session_duration: Option<i64>,
+    /// AWS region of the store, recorded at `init`.
+    #[structopt(long, global = true, env = "AWS_PASS_REGION")]
+    region: Option<String>,
+    /// Profile of the store's credentials file to use.
+    #[structopt(long, global = true, env = "AWS_PASS_PROFILE")]
+    profile: Option<String>,
+    /// Secrets Manager endpoint, e.g. of a VPC or FIPS endpoint or of LocalStack.
+    #[structopt(long, global = true, env = "AWS_PASS_ENDPOINT_URL")]
+    endpoint_url: Option<String>,
+    /// STS endpoint, e.g. of a VPC or FIPS endpoint or of LocalStack.
+    #[structopt(long, global = true, env = "AWS_PASS_STS_ENDPOINT_URL")]
+    sts_endpoint_url: Option<String>,
+    /// Output format: text, json, yaml or csv. Errors are JSON on stderr unless the format is text.
+    #[structopt(long, global = true, env = "AWS_PASS_OUTPUT", default_value = "text")]
+    output: output::Format,
+    /// Named store to use, configured with `config set stores.<name>.tag_value` and optionally `.prefix`.
+    #[structopt(long, global = true, env = "AWS_PASS_STORE")]
+    store: Option<String>,
     #[structopt(subcommand)]
     cmd: Command,
 }
 
+/// A command read by the shell, without the global flags, which are fixed for the session.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "aws-pass", global_settings = &[AppSettings::NoBinaryName, AppSettings::VersionlessSubcommands])]
+struct ShellLine {
+    #[structopt(subcommand)]
+    cmd: Command,
+}
+
+const SHELL_HISTORY_FILENAME: &str = ".shell-history";
+/// The shell's commands, completed as first words.
+const SHELL_COMMANDS: &[&str] = &[
+    "init", "list", "find", "grep", "pick", "show", "insert", "edit", "generate", "remove", "trash", "restore", "mv",
+    "cp", "history", "diff", "rollback", "otp", "mfa-seed", "agent", "stores", "config", "help", "exit",
+];
+
+fn parse_session_duration(s: &str) -> Result<i64, String> {
+    let duration: i64 = s.parse().map_err(|e| format!("{}", e))?;
+    if !(creds::MIN_SESSION_DURATION..=creds::MAX_SESSION_DURATION).contains(&duration) {
+        return Err(format!(
+            "must be between {} and {} seconds",
+            creds::MIN_SESSION_DURATION,
+            creds::MAX_SESSION_DURATION
+        ));
+    }
+    Ok(duration)
+}
+
+fn parse_recovery_days(s: &str) -> Result<i64, String> {
+    let days: i64 = s.parse().map_err(|e| format!("{}", e))?;
+    if !(7..=30).contains(&days) {
+        return Err("must be between 7 and 30 days".to_string());
+    }
+    Ok(days)
+}
+
+fn parse_field_arg(s: &str) -> (String, Option<String>) {
+    match s.split_once('=') {
+        Some((k, v)) => (k.to_string(), Some(v.to_string())),
+        None => (s.to_string(), None),
+    }
+}
+
 #[tokio::main]
 async fn main() {
     env_logger::init();
     let store_dir = env::var("PASSWORD_STORE_DIR")
-        .map(|psd| path::PathBuf::from(psd))
+        .map(path::PathBuf::from)
         .unwrap_or(dirs::home_dir().unwrap().join(".aws-pass"));
     let opt: Opt = Opt::from_args();
-    let pass_store = DefaultPassStore::new(store_dir, &Region::UsEast1);
+    output::set_format(opt.output);
+    if let Err(e) = run(store_dir, opt).await {
+        output::print_error(&e);
+        process::exit(error::kind_and_exit_code(&e).map_or(1, |(_, exit_code)| exit_code));
+    }
+}
+
+async fn run(store_dir: path::PathBuf, opt: Opt) -> Result<()> {
+    // The config can be fixed even when it doesn't resolve to usable settings
+    if let Command::Config { cmd } = opt.cmd {
+        return config(&store_dir, cmd);
+    }
+    if let Command::Stores { cmd } = opt.cmd {
+        return stores(&store_dir, cmd, opt.store.as_deref());
+    }
+    let args = SettingsArgs {
+        region: opt.region,
+        profile: opt.profile,
+        sm_endpoint: opt.endpoint_url,
+        sts_endpoint: opt.sts_endpoint_url,
+        session_duration: opt.session_duration,
+        store: opt.store,
+    };
+    let settings = Settings::resolve(&store_dir, args.clone())?;
+    if let Some(backend) = settings.config.prompt_backend()? {
+        prompt::set_default_backend(backend);
+    }
+    let pass_store = DefaultPassStore::new(store_dir.clone(), &settings);
     match opt.cmd {
+        Command::Shell {} => shell(&store_dir, &args, pass_store.as_ref()).await,
+        cmd => dispatch(&store_dir, &args, pass_store.as_ref(), cmd).await,
+    }
+}
+
+fn config(store_dir: &path::Path, cmd: ConfigCommand) -> Result<()> {
+    match cmd {
+        ConfigCommand::Get { key } => settings::get(store_dir, &key),
+        ConfigCommand::Set { key, value } => settings::set(store_dir, &key, Some(&value)),
+        ConfigCommand::Unset { key } => settings::set(store_dir, &key, None),
+        ConfigCommand::List {} => settings::list(store_dir),
+    }
+}
+
+fn stores(store_dir: &path::Path, cmd: StoresCommand, selected: Option<&str>) -> Result<()> {
+    match cmd {
+        StoresCommand::List {} => settings::list_stores(store_dir, selected),
+    }
+}
+
+/// Runs commands read from the terminal against **pass_store** until `exit` or the end of input. The commands share
+/// the store's credentials provider, and with it one MFA session.
+async fn shell(store_dir: &path::Path, args: &SettingsArgs, pass_store: &dyn PassStore) -> Result<()> {
+    let mut shell = Shell::new(store_dir.join(SHELL_HISTORY_FILENAME), SHELL_COMMANDS)?;
+    // Listing the names obtains the session first, so that the prompt shows its time left
+    shell.set_names(pass_store.names().await?);
+    loop {
+        let expiration = pass_store.session_expiration();
+        let words = match shell.read_line(&shell::prompt(expiration))? {
+            Some(words) => words,
+            None => return Ok(()),
+        };
+        if matches!(words[0].as_str(), "exit" | "quit") {
+            return Ok(());
+        }
+        match ShellLine::from_iter_safe(&words) {
+            Ok(ShellLine { cmd: Command::Shell {} }) => eprintln!("Already in the shell"),
+            Ok(line) => {
+                if let Err(e) = dispatch(store_dir, args, pass_store, line.cmd).await {
+                    output::print_error(&e);
+                }
+                // The names change with the passwords, a failure keeps the names completed so far. Listing them needs
+                // the session, an expired one is left for the next store command to renew
+                let live = pass_store.session_expiration().is_some_and(|e| e > Utc::now());
+                if live {
+                    if let Ok(names) = pass_store.names().await {
+                        shell.set_names(names);
+                    }
+                }
+            }
+            Err(e) if e.kind == ErrorKind::HelpDisplayed => println!("{}", e.message),
+            Err(e) => eprintln!("{}", e.message),
+        }
+    }
+}
+
+/// Runs the store command **cmd** against **pass_store**.
+async fn dispatch(store_dir: &path::Path, args: &SettingsArgs, pass_store: &dyn PassStore, cmd: Command) -> Result<()> {
+    match cmd {
         Command::Init {} => pass_store.init().await,
-        Command::List { prefix } => pass_store.list(prefix.as_deref()).await,
-        Command::Show { name } => pass_store.show(&name).await,
-        Command::Insert { name } => pass_store.insert(&name).await,
+        Command::List { prefix, flat, depth } => pass_store.list(prefix.as_deref(), flat, depth).await,
+        Command::Find { term } => pass_store.find(&term).await,
+        Command::Grep {
+            pattern,
+            prefix,
+            ignore_case,
+            yes,
+        } => {
+            let pattern = RegexBuilder::new(&pattern)
+                .case_insensitive(ignore_case)
+                .build()
+                .map_err(|e| AwsPassError::InvalidInput(e.to_string()))?;
+            pass_store.grep(&pattern, prefix.as_deref(), yes).await
+        }
+        Command::Pick { refresh, timeout } => pass_store.pick(refresh, timeout).await,
+        Command::Show {
+            name,
+            line,
+            field,
+            clip,
+            timeout,
+            version,
+        } => {
+            let selector = line.map(Selector::Line).or(field.map(Selector::Field));
+            pass_store
+                .show(
+                    &name,
+                    selector.as_ref(),
+                    if clip { Some(timeout) } else { None },
+                    version.as_deref(),
+                )
+                .await
+        }
+        Command::Insert {
+            name,
+            echo,
+            stdin,
+            multiline,
+            fields,
+        } => {
+            let mode = if !fields.is_empty() {
+                InsertMode::Fields(fields)
+            } else if stdin || (!echo && !util::stdin_is_tty()) {
+                InsertMode::Stdin
+            } else if multiline {
+                InsertMode::Multiline
+            } else if echo {
+                InsertMode::Echo
+            } else {
+                InsertMode::Hidden
+            };
+            pass_store.insert(&name, mode).await
+        }
         Command::Edit { name } => pass_store.edit(&name).await,
         Command::Generate {
             name,
+            source,
+            policy,
             exclude_chars,
             length,
+            no_lowercase,
+            no_uppercase,
+            no_digits,
+            no_symbols,
+            require_each_type,
+            no_ambiguous,
+            words,
+            separator,
+            wordlist,
+            in_place,
+            force,
         } => {
-            pass_store
-                .generate(&name, exclude_chars.as_deref(), length.as_ref())
-                .await
+            let options = GenerateOptions {
+                source,
+                policy,
+                length,
+                exclude_chars,
+                no_lowercase,
+                no_uppercase,
+                no_digits,
+                no_symbols,
+                require_each_type,
+                no_ambiguous,
+                words,
+                separator,
+                wordlist,
+                in_place,
+                force,
+            };
+            pass_store.generate(&name, &options).await
+        }
+        Command::Remove {
+            name,
+            recursive,
+            yes,
+            dry_run,
+            recovery_days,
+            force,
+        } => {
+            let options = RemoveOptions {
+                recovery_days,
+                force,
+                recursive,
+                yes,
+                dry_run,
+            };
+            pass_store.remove(&name, &options).await
+        }
+        Command::Trash {} => pass_store.trash().await,
+        Command::Restore { name } => pass_store.restore(&name).await,
+        Command::History { name } => pass_store.history(&name).await,
+        Command::Diff { name, from, to, unmask } => pass_store.diff(&name, &from, &to, unmask).await,
+        Command::Rollback { name, version } => pass_store.rollback(&name, &version).await,
+        Command::Mv { from, to, force } => pass_store.mv(&from, &to, force).await,
+        Command::Cp {
+            from,
+            to,
+            force,
+            to_store,
+            to_region,
+        } => {
+            let dest_settings = match to_store.is_some() || to_region.is_some() {
+                true => Some(Settings::resolve(
+                    store_dir,
+                    SettingsArgs {
+                        region: to_region.clone().or_else(|| args.region.clone()),
+                        store: to_store.or_else(|| args.store.clone()),
+                        ..args.clone()
+                    },
+                )?),
+                false => None,
+            };
+            // An endpoint serves a single region, another region can't be reached through it
+            if let Some(settings) = dest_settings.as_ref().filter(|_| to_region.is_some()) {
+                if settings.sm_endpoint.is_some() || settings.sts_endpoint.is_some() {
+                    return Err(AwsPassError::InvalidInput(
+                        "--to-region can't be used with a custom endpoint".to_string(),
+                    )
+                    .into());
+                }
+            }
+            pass_store.cp(&from, &to, force, dest_settings.as_ref()).await
         }
-        Command::Remove { name } => pass_store.remove(&name).await,
+        Command::ClipRestore { timeout } => clipboard::restore_after(timeout),
+        Command::Otp { cmd } => match cmd {
+            OtpCommand::Show {
+                name,
+                clip,
+                timeout,
+                remaining,
+            } => {
+                pass_store
+                    .otp(&name, if clip { Some(timeout) } else { None }, remaining)
+                    .await
+            }
+            OtpCommand::Insert { name, force } => pass_store.insert_otp(&name, force).await,
+            OtpCommand::Uri { name } => pass_store.otp_uri(&name).await,
+        },
+        Command::MfaSeed { cmd } => match cmd {
+            MfaSeedCommand::Set {} => pass_store.set_mfa_seed().await,
+            MfaSeedCommand::Remove {} => pass_store.remove_mfa_seed().await,
+        },
+        Command::Agent { cmd } => match cmd {
+            AgentCommand::Start { idle_timeout } => agent::start(store_dir, idle_timeout).await,
+            AgentCommand::Stop {} => agent::stop(store_dir).await,
+            AgentCommand::Status {} => agent::status(store_dir).await,
+            AgentCommand::Run { idle_timeout } => agent::run(store_dir, idle_timeout).await,
+        },
+        Command::Config { cmd } => config(store_dir, cmd),
+        Command::Stores { cmd } => stores(store_dir, cmd, args.store.as_deref()),
+        Command::Shell {} => Err(AwsPassError::InvalidInput("Already in the shell".to_string()).into()),
     }
 }
@@ -0,0 +1,301 @@
+use crate::error::AwsPassError;
+use anyhow::Result;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OtpKind {
+    /// Time based, RFC 6238.
+    Totp,
+    /// Counter based, RFC 4226.
+    Hotp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// A parsed `otpauth://` URI as used by authenticator apps.
+#[derive(Debug, Clone)]
+pub struct OtpUri {
+    url: Url,
+    pub kind: OtpKind,
+    pub secret: Vec<u8>,
+    pub algorithm: Algorithm,
+    pub digits: u32,
+    pub period: u64,
+    pub counter: u64,
+}
+
+impl OtpUri {
+    /// Parses the given **uri**, e.g. `otpauth://totp/Example:alice?secret=JBSWY3DPEHPK3PXP&issuer=Example`.
+    pub fn parse(uri: &str) -> Result<OtpUri> {
+        let invalid = |message: &str| AwsPassError::InvalidInput(format!("Invalid otpauth URI: {}", message));
+        let url = Url::parse(uri.trim()).map_err(|e| invalid(&e.to_string()))?;
+        if url.scheme() != "otpauth" {
+            return Err(invalid("scheme must be otpauth").into());
+        }
+        let kind = match url.host_str() {
+            Some("totp") => OtpKind::Totp,
+            Some("hotp") => OtpKind::Hotp,
+            _ => return Err(invalid("type must be totp or hotp").into()),
+        };
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.into_owned())
+        };
+        let secret = decode_base32(&param("secret").ok_or_else(|| invalid("missing secret"))?)
+            .ok_or_else(|| invalid("secret must be base32"))?;
+        let algorithm = match param("algorithm").as_deref().map(str::to_uppercase).as_deref() {
+            None | Some("SHA1") => Algorithm::Sha1,
+            Some("SHA256") => Algorithm::Sha256,
+            Some("SHA512") => Algorithm::Sha512,
+            Some(_) => return Err(invalid("algorithm must be SHA1, SHA256 or SHA512").into()),
+        };
+        let digits = param("digits")
+            .map_or(Ok(6), |d| d.parse())
+            .map_err(|_| invalid("bad digits"))?;
+        if !(6..=10).contains(&digits) {
+            return Err(invalid("digits must be between 6 and 10").into());
+        }
+        let period = param("period")
+            .map_or(Ok(30), |p| p.parse())
+            .map_err(|_| invalid("bad period"))?;
+        if period == 0 {
+            return Err(invalid("period must be positive").into());
+        }
+        let counter = match (kind, param("counter")) {
+            (_, Some(c)) => c.parse().map_err(|_| invalid("bad counter"))?,
+            (OtpKind::Hotp, None) => return Err(invalid("hotp requires a counter").into()),
+            (OtpKind::Totp, None) => 0,
+        };
+        Ok(OtpUri {
+            url,
+            kind,
+            secret,
+            algorithm,
+            digits,
+            period,
+            counter,
+        })
+    }
+
+    /// The code for the given unix time **now**, TOTP codes depend on the time and HOTP codes on the counter.
+    pub fn code(&self, now: u64) -> String {
+        match self.kind {
+            OtpKind::Totp => hotp(&self.secret, now / self.period, self.digits, self.algorithm),
+            OtpKind::Hotp => hotp(&self.secret, self.counter, self.digits, self.algorithm),
+        }
+    }
+
+    /// Seconds left before the TOTP code for the given unix time **now** changes.
+    pub fn remaining(&self, now: u64) -> Option<u64> {
+        match self.kind {
+            OtpKind::Totp => Some(self.period - now % self.period),
+            OtpKind::Hotp => None,
+        }
+    }
+
+    /// The URI with its counter advanced by one, for after an HOTP code has been used.
+    pub fn next(&self) -> OtpUri {
+        let counter = self.counter + 1;
+        let mut url = self.url.clone();
+        let mut pairs: Vec<(String, String)> = self
+            .url
+            .query_pairs()
+            .filter(|(k, _)| !k.eq_ignore_ascii_case("counter"))
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+        pairs.push(("counter".to_string(), counter.to_string()));
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+        OtpUri {
+            url,
+            counter,
+            ..self.clone()
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.url.as_str()
+    }
+}
+
+/// The current unix time in seconds.
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Generates the RFC 4226 code for the given **secret** and **counter**.
+pub fn hotp(secret: &[u8], counter: u64, digits: u32, algorithm: Algorithm) -> String {
+    let message = counter.to_be_bytes();
+    let digest = match algorithm {
+        Algorithm::Sha1 => hmac_digest::<Hmac<Sha1>>(secret, &message),
+        Algorithm::Sha256 => hmac_digest::<Hmac<Sha256>>(secret, &message),
+        Algorithm::Sha512 => hmac_digest::<Hmac<Sha512>>(secret, &message),
+    };
+    // Dynamic truncation, the low nibble of the last byte picks 4 bytes of the digest
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    let code = u64::from(binary) % 10u64.pow(digits);
+    format!("{:0width$}", code, width = digits as usize)
+}
+
+fn hmac_digest<M: Mac + hmac::digest::KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = <M as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Decodes RFC 4648 base32, ignoring case, padding and whitespace as authenticator secrets often carry them.
+pub fn decode_base32(encoded: &str) -> Option<Vec<u8>> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut bytes = Vec::new();
+    let mut buffer: u64 = 0;
+    let mut bits = 0;
+    for c in encoded.chars().filter(|c| !c.is_whitespace() && *c != '=' && *c != '-') {
+        let value = ALPHABET.iter().position(|a| *a as char == c.to_ascii_uppercase())? as u64;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    if bytes.is_empty() {
+        return None;
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED_SHA1: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    const SEED_SHA256: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZA====";
+    const SEED_SHA512: &str =
+        "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNA=";
+
+    #[test]
+    fn hotp_matches_rfc_4226_appendix_d() {
+        let codes = [
+            "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583", "399871", "520489",
+        ];
+        for (counter, code) in codes.iter().enumerate() {
+            assert_eq!(hotp(b"12345678901234567890", counter as u64, 6, Algorithm::Sha1), *code);
+        }
+    }
+
+    #[test]
+    fn totp_matches_rfc_6238_appendix_b() {
+        let vectors: [(u64, [&str; 3]); 6] = [
+            (59, ["94287082", "46119246", "90693936"]),
+            (1111111109, ["07081804", "68084774", "25091201"]),
+            (1111111111, ["14050471", "67062674", "99943326"]),
+            (1234567890, ["89005924", "91819424", "93441116"]),
+            (2000000000, ["69279037", "90698825", "38618901"]),
+            (20000000000, ["65353130", "77737706", "47863826"]),
+        ];
+        let uris = [("SHA1", SEED_SHA1), ("SHA256", SEED_SHA256), ("SHA512", SEED_SHA512)].map(|(algorithm, seed)| {
+            OtpUri::parse(&format!(
+                "otpauth://totp/Example:alice?secret={}&algorithm={}&digits=8",
+                seed, algorithm
+            ))
+            .unwrap()
+        });
+        for (now, codes) in vectors {
+            for (uri, code) in uris.iter().zip(codes) {
+                assert_eq!(uri.code(now), code, "{:?} at {}", uri.algorithm, now);
+            }
+        }
+    }
+
+    #[test]
+    fn totp_remaining() {
+        let uri = OtpUri::parse(&format!("otpauth://totp/x?secret={}", SEED_SHA1)).unwrap();
+        assert_eq!(uri.remaining(59), Some(1));
+        assert_eq!(uri.remaining(60), Some(30));
+    }
+
+    #[test]
+    fn decodes_rfc_4648_base32() {
+        for (decoded, encoded) in [
+            ("f", "MY======"),
+            ("fo", "MZXQ===="),
+            ("foo", "MZXW6==="),
+            ("foob", "MZXW6YQ="),
+            ("fooba", "MZXW6YTB"),
+            ("foobar", "MZXW6YTBOI======"),
+        ] {
+            assert_eq!(
+                decode_base32(encoded).as_deref(),
+                Some(decoded.as_bytes()),
+                "{}",
+                encoded
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_base32_ignoring_case_whitespace_and_dashes() {
+        assert_eq!(decode_base32("mzxw 6ytb-oi").as_deref(), Some(&b"foobar"[..]));
+    }
+
+    #[test]
+    fn rejects_bad_base32() {
+        assert_eq!(decode_base32(""), None);
+        assert_eq!(decode_base32("===="), None);
+        assert_eq!(decode_base32("MZXW1"), None);
+    }
+
+    #[test]
+    fn hotp_counter_advances() {
+        let uri = OtpUri::parse(&format!(
+            "otpauth://hotp/Example:alice?secret={}&issuer=Example&counter=1",
+            SEED_SHA1
+        ))
+        .unwrap();
+        assert_eq!(uri.code(0), "287082");
+        assert_eq!(uri.remaining(0), None);
+        let next = uri.next();
+        assert_eq!(next.counter, 2);
+        assert_eq!(next.code(0), "359152");
+        assert_eq!(
+            next.as_str(),
+            format!(
+                "otpauth://hotp/Example:alice?secret={}&issuer=Example&counter=2",
+                SEED_SHA1
+            )
+        );
+        assert_eq!(OtpUri::parse(next.as_str()).unwrap().counter, 2);
+    }
+
+    #[test]
+    fn rejects_bad_uris() {
+        for uri in [
+            "https://totp/x?secret=MZXW6YTB",
+            "otpauth://motp/x?secret=MZXW6YTB",
+            "otpauth://totp/x",
+            "otpauth://totp/x?secret=not-base32!",
+            "otpauth://totp/x?secret=MZXW6YTB&algorithm=MD5",
+            "otpauth://totp/x?secret=MZXW6YTB&digits=5",
+            "otpauth://totp/x?secret=MZXW6YTB&period=0",
+            "otpauth://hotp/x?secret=MZXW6YTB",
+        ] {
+            assert!(OtpUri::parse(uri).is_err(), "{}", uri);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::error::{self, AwsPassError};
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Map, Value};
+use std::{io, str::FromStr, sync::OnceLock};
+
+/// The output formats, `--output` selects one by name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Plain text for people, each command's own.
+    Text,
+    Json,
+    Yaml,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = AwsPassError;
+
+    fn from_str(s: &str) -> Result<Format, AwsPassError> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "yaml" => Ok(Format::Yaml),
+            "csv" => Ok(Format::Csv),
+            _ => Err(AwsPassError::InvalidInput(format!(
+                "Unknown output format {}, expected text, json, yaml or csv",
+                s
+            ))),
+        }
+    }
+}
+
+static FORMAT: OnceLock<Format> = OnceLock::new();
+
+/// Sets the output format, only the first call has an effect.
+pub fn set_format(format: Format) {
+    let _ = FORMAT.set(format);
+}
+
+/// Whether output is plain text, otherwise commands print their results with **print**.
+pub fn is_text() -> bool {
+    *FORMAT.get().unwrap_or(&Format::Text) == Format::Text
+}
+
+/// Prints **value** in the selected format. As CSV an array of objects is a row per object and an object a single
+/// row, with nested values given as JSON.
+pub fn print(value: &Value) -> Result<()> {
+    match FORMAT.get().unwrap_or(&Format::Text) {
+        Format::Text | Format::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        Format::Yaml => print!("{}", serde_yaml::to_string(value)?),
+        Format::Csv => print_csv(value)?,
+    }
+    Ok(())
+}
+
+/// Prints **err** to stderr, as a JSON object unless the output is text.
+pub fn print_error(err: &anyhow::Error) {
+    let (kind, exit_code) = error::kind_and_exit_code(err).unwrap_or(("Error", 1));
+    let message = match err.downcast_ref::<AwsPassError>() {
+        Some(e) => e.to_string(),
+        None => format!("{:#}", err),
+    };
+    match is_text() {
+        true => eprintln!("{}", message),
+        false => eprintln!(
+            "{}",
+            json!({"error": {"kind": kind, "message": message, "exit_code": exit_code}})
+        ),
+    }
+}
+
+/// A date as RFC 3339, or null.
+pub fn date(date: Option<DateTime<Utc>>) -> Value {
+    date.map_or(Value::Null, |d| {
+        Value::String(d.to_rfc3339_opts(SecondsFormat::Secs, true))
+    })
+}
+
+fn print_csv(value: &Value) -> Result<()> {
+    let rows: Vec<&Map<String, Value>> = match value {
+        Value::Array(values) => values.iter().filter_map(|v| v.as_object()).collect(),
+        Value::Object(map) => vec![map],
+        _ => Vec::new(),
+    };
+    let mut columns: Vec<&String> = Vec::new();
+    for row in &rows {
+        for key in row.keys() {
+            if !columns.contains(&key) {
+                columns.push(key);
+            }
+        }
+    }
+    let mut writer = csv::Writer::from_writer(io::stdout());
+    writer.write_record(&columns)?;
+    for row in rows {
+        writer.write_record(columns.iter().map(|c| match row.get(*c) {
+            None | Some(Value::Null) => String::new(),
+            Some(Value::String(s)) => s.clone(),
+            Some(v) => v.to_string(),
+        }))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
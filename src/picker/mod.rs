@@ -0,0 +1,175 @@
+use crate::fuzzy;
+use anyhow::Result;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, ClearType},
+};
+use std::io::{self, Stderr, Write};
+
+const HELP: &str = "enter show  ^y clip  ^e edit  ^o otp  ^u copy username  esc quit";
+
+/// What to do with the picked item.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Show,
+    Clip,
+    Edit,
+    Otp,
+    Username,
+}
+
+/// Lets the user fuzzy find one of **names** on the terminal, previewing the item under the cursor with the lines
+/// **preview** gives for its index. Returns the index picked and the action chosen for it, none when cancelled. The
+/// picker is drawn on stderr so that the action's output on stdout can still be piped.
+pub fn pick<F>(names: &[String], preview: F) -> Result<Option<(usize, Action)>>
+where
+    F: Fn(usize) -> Vec<String>,
+{
+    let mut out = io::stderr();
+    terminal::enable_raw_mode()?;
+    execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let picked = run(&mut out, names, preview);
+    execute!(out, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    picked
+}
+
+fn run<F>(out: &mut Stderr, names: &[String], preview: F) -> Result<Option<(usize, Action)>>
+where
+    F: Fn(usize) -> Vec<String>,
+{
+    let mut query = String::new();
+    let mut selected = 0;
+    let all: Vec<(usize, &str)> = names.iter().map(String::as_str).enumerate().collect();
+    let mut matches = fuzzy::rank(&query, all.clone(), |(_, name)| name);
+    loop {
+        draw(out, names, &query, &matches, selected, &preview)?;
+        let key = match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => key,
+            _ => continue,
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let action = match key {
+            KeyEvent { code: KeyCode::Esc, .. } => return Ok(None),
+            KeyEvent {
+                code: KeyCode::Char('c' | 'g'),
+                ..
+            } if ctrl => return Ok(None),
+            KeyEvent {
+                code: KeyCode::Enter, ..
+            } => Some(Action::Show),
+            KeyEvent {
+                code: KeyCode::Char(c), ..
+            } if ctrl => match c {
+                'y' => Some(Action::Clip),
+                'e' => Some(Action::Edit),
+                'o' => Some(Action::Otp),
+                'u' => Some(Action::Username),
+                'p' => {
+                    selected = selected.saturating_sub(1);
+                    None
+                }
+                'n' => {
+                    selected += 1;
+                    None
+                }
+                _ => None,
+            },
+            KeyEvent { code: KeyCode::Up, .. } => {
+                selected = selected.saturating_sub(1);
+                None
+            }
+            KeyEvent {
+                code: KeyCode::Down, ..
+            } => {
+                selected += 1;
+                None
+            }
+            KeyEvent {
+                code: KeyCode::Backspace,
+                ..
+            } => {
+                query.pop();
+                matches = fuzzy::rank(&query, all.clone(), |(_, name)| name);
+                selected = 0;
+                None
+            }
+            KeyEvent {
+                code: KeyCode::Char(c), ..
+            } => {
+                query.push(c);
+                matches = fuzzy::rank(&query, matches, |(_, name)| name);
+                selected = 0;
+                None
+            }
+            _ => None,
+        };
+        selected = selected.min(matches.len().saturating_sub(1));
+        if let (Some(action), Some(&(index, _))) = (action, matches.get(selected)) {
+            return Ok(Some((index, action)));
+        }
+    }
+}
+
+/// Draws the query line, the matches around the selected one, a preview of the selected one and the keys' help.
+fn draw<F>(
+    out: &mut Stderr,
+    names: &[String],
+    query: &str,
+    matches: &[(usize, &str)],
+    selected: usize,
+    preview: &F,
+) -> Result<()>
+where
+    F: Fn(usize) -> Vec<String>,
+{
+    let (cols, rows) = terminal::size()?;
+    let (cols, rows) = (cols as usize, rows as usize);
+    let list_rows = (rows.saturating_sub(3) / 2).max(1);
+    let first = selected.saturating_sub(list_rows - 1);
+    queue!(out, terminal::Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+    let prompt = format!("> {}  {}/{}", query, matches.len(), names.len());
+    queue!(out, Print(truncate(&prompt, cols)))?;
+    for (row, (_, name)) in matches.iter().skip(first).take(list_rows).enumerate() {
+        queue!(out, cursor::MoveTo(0, (row + 1) as u16))?;
+        let line = truncate(&format!("  {}", name), cols);
+        if first + row == selected {
+            queue!(
+                out,
+                SetAttribute(Attribute::Reverse),
+                Print(line),
+                SetAttribute(Attribute::Reset)
+            )?;
+        } else {
+            queue!(out, Print(line))?;
+        }
+    }
+    let separator_row = list_rows + 1;
+    queue!(out, cursor::MoveTo(0, separator_row as u16), Print("─".repeat(cols)))?;
+    if let Some(&(index, _)) = matches.get(selected) {
+        let preview_rows = rows.saturating_sub(separator_row + 2);
+        for (row, line) in preview(index).iter().take(preview_rows).enumerate() {
+            queue!(
+                out,
+                cursor::MoveTo(0, (separator_row + 1 + row) as u16),
+                Print(truncate(line, cols))
+            )?;
+        }
+    }
+    queue!(
+        out,
+        cursor::MoveTo(0, rows.saturating_sub(1) as u16),
+        SetAttribute(Attribute::Dim),
+        Print(truncate(HELP, cols)),
+        SetAttribute(Attribute::Reset)
+    )?;
+    out.flush()?;
+    Ok(())
+}
+
+fn truncate(s: &str, cols: usize) -> String {
+    s.chars().take(cols).collect()
+}
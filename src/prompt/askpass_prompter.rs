@@ -0,0 +1,43 @@
+use super::Prompter;
+use crate::error::AwsPassError;
+use anyhow::{anyhow, Result};
+use std::{
+    env,
+    process::{Command, Stdio},
+};
+
+/// Asks with an `SSH_ASKPASS` style program, which is given the message as its argument and prints the value.
+pub struct AskpassPrompter {
+    program: String,
+}
+
+impl AskpassPrompter {
+    /// Uses the program named by `AWS_PASS_ASKPASS`, falling back to `SSH_ASKPASS`.
+    pub fn new() -> Result<AskpassPrompter> {
+        env::var("AWS_PASS_ASKPASS")
+            .or_else(|_| env::var("SSH_ASKPASS"))
+            .map(|program| AskpassPrompter { program })
+            .map_err(|_| {
+                AwsPassError::InvalidInput("Neither AWS_PASS_ASKPASS nor SSH_ASKPASS is set".to_string()).into()
+            })
+    }
+}
+
+impl Prompter for AskpassPrompter {
+    fn prompt(&self, _key: &str, message: &str, _hidden: bool) -> Result<String> {
+        let output = Command::new(&self.program)
+            .arg(message)
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit())
+            .output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "{} was cancelled or failed with {}",
+                self.program,
+                output.status
+            ));
+        }
+        let value = String::from_utf8(output.stdout)?;
+        Ok(value.trim_end_matches(&['\r', '\n'][..]).to_string())
+    }
+}
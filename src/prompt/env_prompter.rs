@@ -0,0 +1,23 @@
+use super::Prompter;
+use crate::error::AwsPassError;
+use anyhow::Result;
+use std::env;
+
+/// Takes values from environment variables named after the prompt's key, e.g. `AWS_PASS_MFA_TOKEN_CODE` for the
+/// `mfa-token-code` prompt.
+pub struct EnvPrompter {}
+
+impl EnvPrompter {
+    pub fn var_name(key: &str) -> String {
+        format!("AWS_PASS_{}", key.to_uppercase().replace('-', "_"))
+    }
+}
+
+impl Prompter for EnvPrompter {
+    fn prompt(&self, key: &str, message: &str, _hidden: bool) -> Result<String> {
+        let var_name = EnvPrompter::var_name(key);
+        env::var(&var_name).map_err(|_| {
+            AwsPassError::InvalidInput(format!("{} asked for {} but it isn't set", message, var_name)).into()
+        })
+    }
+}
@@ -0,0 +1,116 @@
+pub mod askpass_prompter;
+pub mod env_prompter;
+pub mod pinentry_prompter;
+pub mod tty_prompter;
+
+use crate::{
+    error::AwsPassError,
+    util::{find_on_path, stdin_is_tty},
+};
+use anyhow::Result;
+use askpass_prompter::AskpassPrompter;
+use env_prompter::EnvPrompter;
+use pinentry_prompter::PinentryPrompter;
+use std::{env, str::FromStr, sync::OnceLock};
+use tty_prompter::TtyPrompter;
+
+pub trait Prompter {
+    /// Asks for a value given the prompt's **key**, e.g. `mfa-token-code`, and its **message**. **hidden** values are
+    /// not echoed where the backend allows it.
+    fn prompt(&self, key: &str, message: &str, hidden: bool) -> Result<String>;
+}
+
+/// The prompt backends, `AWS_PASS_PROMPT` selects one by name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    /// Picks a backend per prompt, see **prompter**.
+    Auto,
+    Tty,
+    Pinentry,
+    Askpass,
+    Env,
+}
+
+impl FromStr for Backend {
+    type Err = AwsPassError;
+
+    fn from_str(s: &str) -> Result<Backend, AwsPassError> {
+        match s {
+            "auto" => Ok(Backend::Auto),
+            "tty" => Ok(Backend::Tty),
+            "pinentry" => Ok(Backend::Pinentry),
+            "askpass" => Ok(Backend::Askpass),
+            "env" => Ok(Backend::Env),
+            _ => Err(AwsPassError::InvalidInput(format!(
+                "Unknown prompt backend {}, expected auto, tty, pinentry, askpass or env",
+                s
+            ))),
+        }
+    }
+}
+
+static DEFAULT_BACKEND: OnceLock<Backend> = OnceLock::new();
+
+/// Sets the backend used when `AWS_PASS_PROMPT` isn't set, only the first call has an effect.
+pub fn set_default_backend(backend: Backend) {
+    let _ = DEFAULT_BACKEND.set(backend);
+}
+
+/// The prompter for the given **key**. Unless a backend is selected the value is taken from the key's environment
+/// variable if set, then asked for on the terminal if stdin is one, then with an askpass program if one is configured
+/// and finally with `pinentry`.
+pub fn prompter(key: &str) -> Result<Box<dyn Prompter>> {
+    let backend = match env::var("AWS_PASS_PROMPT") {
+        Ok(name) => name.parse()?,
+        Err(_) => *DEFAULT_BACKEND.get().unwrap_or(&Backend::Auto),
+    };
+    Ok(match backend {
+        Backend::Tty => Box::new(TtyPrompter {}),
+        Backend::Pinentry => Box::new(PinentryPrompter::new()),
+        Backend::Askpass => Box::new(AskpassPrompter::new()?),
+        Backend::Env => Box::new(EnvPrompter {}),
+        Backend::Auto if env::var_os(EnvPrompter::var_name(key)).is_some() => Box::new(EnvPrompter {}),
+        Backend::Auto if stdin_is_tty() => Box::new(TtyPrompter {}),
+        Backend::Auto => match AskpassPrompter::new() {
+            Ok(askpass) => Box::new(askpass),
+            Err(_) if find_on_path(&PinentryPrompter::program()).is_some() => Box::new(PinentryPrompter::new()),
+            Err(_) => Box::new(TtyPrompter {}),
+        },
+    })
+}
+
+/// Asks for a value, echoing it.
+pub fn ask(key: &str, message: &str) -> Result<String> {
+    prompter(key)?.prompt(key, message, false)
+}
+
+/// Asks for a value without echoing it.
+pub fn ask_hidden(key: &str, message: &str) -> Result<String> {
+    prompter(key)?.prompt(key, message, true)
+}
+
+/// Asks twice for a value without echoing it, failing if the two entries differ.
+pub fn ask_hidden_confirmed(key: &str, message: &str) -> Result<String> {
+    let prompter = prompter(key)?;
+    let value = prompter.prompt(key, message, true)?;
+    let confirmation = prompter.prompt(key, &format!("Retype {}", message.to_lowercase()), true)?;
+    if value != confirmation {
+        return Err(AwsPassError::InvalidInput("Entries do not match".to_string()).into());
+    }
+    Ok(value)
+}
+
+/// Asks for a value given its **name**, failing if it's empty.
+pub fn ask_non_empty(key: &str, name: &str, hidden: bool) -> Result<String> {
+    let value = prompter(key)?.prompt(key, &format!("{}:", name), hidden)?;
+    if value.is_empty() {
+        return Err(AwsPassError::InvalidInput(format!("{} must be non-empty", name)).into());
+    }
+    Ok(value)
+}
+
+/// Asks a yes or no **question**, only an answer of `y` or `yes` is a yes.
+pub fn confirm(key: &str, question: &str) -> Result<bool> {
+    let answer = prompter(key)?.prompt(key, &format!("{} [y/N]", question), false)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
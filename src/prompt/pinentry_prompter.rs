@@ -0,0 +1,197 @@
+use super::Prompter;
+use anyhow::{anyhow, Result};
+use std::{
+    env,
+    io::{BufRead, BufReader, Write},
+    process::{ChildStdin, ChildStdout, Command, Stdio},
+};
+
+/// Asks with `pinentry` over the Assuan protocol, `AWS_PASS_PINENTRY` names another pinentry program. Pinentry never
+/// echoes, so values are always hidden.
+pub struct PinentryPrompter {
+    program: String,
+}
+
+impl PinentryPrompter {
+    pub fn new() -> PinentryPrompter {
+        PinentryPrompter {
+            program: PinentryPrompter::program(),
+        }
+    }
+
+    pub fn program() -> String {
+        env::var("AWS_PASS_PINENTRY").unwrap_or_else(|_| "pinentry".to_string())
+    }
+}
+
+impl Prompter for PinentryPrompter {
+    fn prompt(&self, _key: &str, message: &str, _hidden: bool) -> Result<String> {
+        let mut child = Command::new(&self.program)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let mut session = Session {
+            stdin: child
+                .stdin
+                .take()
+                .ok_or_else(|| anyhow!("Could not write to pinentry"))?,
+            stdout: BufReader::new(
+                child
+                    .stdout
+                    .take()
+                    .ok_or_else(|| anyhow!("Could not read from pinentry"))?,
+            ),
+        };
+        let result = session.get_pin(message);
+        // Closing stdin ends the pinentry session
+        drop(session);
+        child.wait()?;
+        result
+    }
+}
+
+struct Session {
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Session {
+    fn get_pin(&mut self, message: &str) -> Result<String> {
+        self.read_response()?;
+        self.command(&format!("SETDESC {}", escape(message)))?;
+        self.command("SETPROMPT aws-pass")?;
+        let data = self.command("GETPIN")?;
+        self.command("BYE")?;
+        Ok(data)
+    }
+
+    /// Sends an Assuan command, returns the data lines of its response.
+    fn command(&mut self, command: &str) -> Result<String> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()?;
+        self.read_response()
+    }
+
+    fn read_response(&mut self) -> Result<String> {
+        let mut data = String::new();
+        loop {
+            let mut line = String::new();
+            if self.stdout.read_line(&mut line)? == 0 {
+                return Err(anyhow!("pinentry closed the connection"));
+            }
+            let line = line.trim_end_matches(&['\r', '\n'][..]);
+            if line == "OK" || line.starts_with("OK ") {
+                return Ok(data);
+            } else if let Some(d) = line.strip_prefix("D ") {
+                data.push_str(&unescape(d)?);
+            } else if let Some(error) = line.strip_prefix("ERR ") {
+                return Err(anyhow!("pinentry failed: {}", error));
+            }
+            // Status and comment lines are ignored
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('%', "%25").replace('\n', "%0A").replace('\r', "%0D")
+}
+
+fn unescape(s: &str) -> Result<String> {
+    let mut bytes = Vec::new();
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex: String = iter.by_ref().take(2).map(char::from).collect();
+            if hex.len() != 2 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(anyhow!("Malformed pinentry data"));
+            }
+            bytes.push(u8::from_str_radix(&hex, 16).map_err(|_| anyhow!("Malformed pinentry data"))?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    Ok(String::from_utf8(bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, os::unix::fs::PermissionsExt, path::Path};
+    use tempfile::TempDir;
+
+    /// A fake pinentry answering `GETPIN` with **getpin_response** and logging the commands it receives.
+    fn fake_pinentry(dir: &Path, getpin_response: &str) -> PinentryPrompter {
+        let path = dir.join("pinentry");
+        let script = format!(
+            r#"#!/bin/sh
+echo "OK Pleased to meet you"
+while read -r line; do
+  echo "$line" >> "{log}"
+  case "$line" in
+    GETPIN) printf '%s\n' {response} ;;
+    BYE) echo "OK closing connection"; exit 0 ;;
+    *) echo "OK" ;;
+  esac
+done
+"#,
+            log = dir.join("log").display(),
+            response = getpin_response
+        );
+        fs::write(&path, script).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        PinentryPrompter {
+            program: path.display().to_string(),
+        }
+    }
+
+    #[test]
+    fn reads_data_lines_unescaped() {
+        let dir = TempDir::new().unwrap();
+        let pinentry = fake_pinentry(
+            dir.path(),
+            r##""S PASSWORD_FROM_CACHE" "# a comment" "D p%25ss%0Aw" "D %C3%A9rd" "OK""##,
+        );
+        let pin = pinentry
+            .prompt("mfa-token-code", "MFA token code:\n100% yours", true)
+            .unwrap();
+        assert_eq!(pin, "p%ss\nwérd");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("log")).unwrap(),
+            "SETDESC MFA token code:%0A100%25 yours\nSETPROMPT aws-pass\nGETPIN\nBYE\n"
+        );
+    }
+
+    #[test]
+    fn reads_empty_pin() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(fake_pinentry(dir.path(), "OK").prompt("k", "m", true).unwrap(), "");
+    }
+
+    #[test]
+    fn fails_on_err() {
+        let dir = TempDir::new().unwrap();
+        let pinentry = fake_pinentry(dir.path(), r#""ERR 83886179 Operation cancelled <Pinentry>""#);
+        let err = pinentry.prompt("k", "m", true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "pinentry failed: 83886179 Operation cancelled <Pinentry>"
+        );
+    }
+
+    #[test]
+    fn fails_when_pinentry_exits() {
+        let dir = TempDir::new().unwrap();
+        let pinentry = fake_pinentry(dir.path(), r#""D 1234"; exit 0; echo"#);
+        assert!(pinentry.prompt("k", "m", true).is_err());
+    }
+
+    #[test]
+    fn unescapes_percent_escapes() {
+        assert_eq!(unescape("a%25b%0D%0Ac").unwrap(), "a%b\r\nc");
+        assert_eq!(unescape("%e2%82%ac").unwrap(), "€");
+        for malformed in ["%", "%4", "%+4", "%zz", "%FF"] {
+            assert!(unescape(malformed).is_err(), "{}", malformed);
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use super::Prompter;
+use anyhow::Result;
+use std::io::{self, BufRead};
+
+/// Asks on the terminal, hidden values are read from the controlling terminal with echo turned off.
+pub struct TtyPrompter {}
+
+impl Prompter for TtyPrompter {
+    fn prompt(&self, _key: &str, message: &str, hidden: bool) -> Result<String> {
+        if hidden {
+            return Ok(rpassword::prompt_password(format!("{} ", message))?);
+        }
+        // Prompts go to stderr so that they don't end up in piped or structured output
+        eprintln!("{}", message);
+        let mut value = String::new();
+        io::stdin().lock().read_line(&mut value)?;
+        Ok(value.trim_end().to_string())
+    }
+}
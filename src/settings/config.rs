@@ -0,0 +1,667 @@
+use crate::{
+    creds::{MAX_SESSION_DURATION, MIN_SESSION_DURATION},
+    error::AwsPassError,
+    generator::Source,
+    prompt::Backend,
+    util::write_private_file,
+};
+use anyhow::Result;
+use rusoto_core::Region;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path, str::FromStr};
+
+pub const CONFIG_FILENAME: &str = "config.toml";
+/// The version of the config written by this build, older configs are migrated when loaded.
+pub const CONFIG_VERSION: u32 = 1;
+/// The region of stores created before `config.toml`, which always used it.
+const LEGACY_REGION: &str = "us-east-1";
+/// Present in every initialized store, a store holding it but no config predates `config.toml`.
+pub const CREDENTIALS_FILENAME: &str = ".credentials";
+
+/// The longest password `generate` creates.
+pub const MAX_LENGTH: i64 = 4096;
+/// The most words of a passphrase `generate` creates.
+pub const MAX_WORDS: i64 = 64;
+/// The name of the store configured by the `store` section.
+pub const DEFAULT_STORE: &str = "default";
+
+/// The keys `config get` and `config set` accept, as `section.key`, besides the `stores.<name>.<key>` keys of
+/// **STORE_KEYS** and the `policies.<name>.<key>` keys of **POLICY_KEYS**.
+pub const KEYS: [&str; 14] = [
+    "aws.region",
+    "aws.profile",
+    "aws.endpoint_url",
+    "aws.sts_endpoint_url",
+    "session.duration",
+    "store.tag_key",
+    "store.tag_value",
+    "store.kms_key_id",
+    "generate.length",
+    "generate.exclude_chars",
+    "generate.source",
+    "generate.policy",
+    "generate.wordlist",
+    "prompt.backend",
+];
+
+/// The keys of a named store, as `stores.<name>.<key>`.
+pub const STORE_KEYS: [&str; 3] = ["tag_value", "prefix", "kms_key_id"];
+
+/// The keys of a password policy, as `policies.<name>.<key>`.
+pub const POLICY_KEYS: [&str; 10] = [
+    "length",
+    "lowercase",
+    "uppercase",
+    "digits",
+    "symbols",
+    "require_each_type",
+    "exclude_ambiguous",
+    "exclude_chars",
+    "words",
+    "separator",
+];
+
+/// The store's `config.toml`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub version: u32,
+    pub aws: AwsConfig,
+    pub session: SessionConfig,
+    pub store: StoreConfig,
+    pub generate: GenerateConfig,
+    pub prompt: PromptConfig,
+    /// Further stores in the same account, by name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub stores: BTreeMap<String, NamedStoreConfig>,
+    /// Password policies for `generate --policy`, by name, on top of the built-in ones.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub policies: BTreeMap<String, PolicyConfig>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AwsConfig {
+    pub region: Option<String>,
+    pub profile: Option<String>,
+    pub endpoint_url: Option<String>,
+    pub sts_endpoint_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionConfig {
+    /// Lifetime in seconds of the MFA session.
+    pub duration: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StoreConfig {
+    /// The tag marking secrets as part of the store.
+    pub tag_key: String,
+    pub tag_value: String,
+    /// The KMS key new secrets are encrypted with, the account's `aws/secretsmanager` key when not set.
+    pub kms_key_id: Option<String>,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        StoreConfig {
+            tag_key: "aws-pass".to_string(),
+            tag_value: "true".to_string(),
+            kms_key_id: None,
+        }
+    }
+}
+
+/// A store sharing the account with the default one, scoped by a value of the store tag, a name prefix or both.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NamedStoreConfig {
+    /// The value of `store.tag_key` marking secrets as part of the store, required.
+    pub tag_value: Option<String>,
+    /// The prefix of the names of the store's secrets, e.g. `team/`, left out of the names the store shows.
+    pub prefix: Option<String>,
+    /// The KMS key new secrets are encrypted with, `store.kms_key_id` when not set.
+    pub kms_key_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenerateConfig {
+    pub length: Option<i64>,
+    pub exclude_chars: Option<String>,
+    /// Where passwords come from, `aws` or `local`, `local` when not set.
+    pub source: Option<String>,
+    /// The policy used when `generate` isn't given one.
+    pub policy: Option<String>,
+    /// A file of words for passphrases, e.g. the EFF large wordlist, the built-in list when not set.
+    pub wordlist: Option<String>,
+}
+
+/// A password policy, what it doesn't set is left to the defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicyConfig {
+    pub length: Option<i64>,
+    pub lowercase: Option<bool>,
+    pub uppercase: Option<bool>,
+    pub digits: Option<bool>,
+    pub symbols: Option<bool>,
+    pub require_each_type: Option<bool>,
+    pub exclude_ambiguous: Option<bool>,
+    pub exclude_chars: Option<String>,
+    /// Makes the policy's passwords passphrases of this many words.
+    pub words: Option<i64>,
+    pub separator: Option<String>,
+}
+
+/// The built-in policy **name**, configured policies of the same name replace them.
+fn builtin_policy(name: &str) -> Option<PolicyConfig> {
+    match name {
+        // The AWS console's default password policy: three classes out of four, symbols of its own set
+        "aws-console" => Some(PolicyConfig {
+            length: Some(20),
+            require_each_type: Some(true),
+            exclude_chars: Some("\",./:;<>?\\`~".to_string()),
+            ..Default::default()
+        }),
+        "pin6" => Some(PolicyConfig {
+            length: Some(6),
+            lowercase: Some(false),
+            uppercase: Some(false),
+            symbols: Some(false),
+            require_each_type: Some(false),
+            ..Default::default()
+        }),
+        "passphrase" => Some(PolicyConfig {
+            words: Some(6),
+            ..Default::default()
+        }),
+        _ => None,
+    }
+}
+
+/// The names of the built-in policies.
+pub const BUILTIN_POLICIES: [&str; 3] = ["aws-console", "pin6", "passphrase"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PromptConfig {
+    pub backend: Option<String>,
+}
+
+impl Config {
+    /// A config for a new store.
+    pub fn new() -> Config {
+        Config {
+            version: CONFIG_VERSION,
+            ..Default::default()
+        }
+    }
+
+    /// Loads the config of the store at **store_dir**. Stores initialized before `config.toml` existed get one written,
+    /// recording the region they were created in, and stores without one yet get the defaults.
+    pub fn load(store_dir: &Path) -> Result<Config> {
+        let config_path = store_dir.join(CONFIG_FILENAME);
+        if !config_path.exists() {
+            return match store_dir.join(CREDENTIALS_FILENAME).exists() {
+                true => Config::migrate_legacy(store_dir),
+                false => Ok(Config::new()),
+            };
+        }
+        let contents = fs::read_to_string(&config_path)?;
+        let mut config: Config = toml::from_str(&contents)
+            .map_err(|e| AwsPassError::InvalidInput(format!("Could not parse {}: {}", config_path.display(), e)))?;
+        if config.version > CONFIG_VERSION {
+            return Err(AwsPassError::InvalidInput(format!(
+                "{} has version {}, this aws-pass only understands up to version {}, please upgrade",
+                config_path.display(),
+                config.version,
+                CONFIG_VERSION
+            ))
+            .into());
+        }
+        config
+            .validate()
+            .map_err(|e| AwsPassError::InvalidInput(format!("{} in {}", e, config_path.display())))?;
+        if config.version < CONFIG_VERSION {
+            // Versions only add settings so far, bumping the version is all there is to migrate
+            config.version = CONFIG_VERSION;
+            config.save(store_dir)?;
+        }
+        Ok(config)
+    }
+
+    fn migrate_legacy(store_dir: &Path) -> Result<Config> {
+        let mut config = Config::new();
+        config.aws.region = Some(LEGACY_REGION.to_string());
+        config.save(store_dir)?;
+        eprintln!("Migrated store config to {}", store_dir.join(CONFIG_FILENAME).display());
+        Ok(config)
+    }
+
+    /// Writes the config to the store at **store_dir**.
+    pub fn save(&self, store_dir: &Path) -> Result<()> {
+        let contents = toml::to_string(self)?;
+        write_private_file(&store_dir.join(CONFIG_FILENAME), contents.as_bytes())?;
+        Ok(())
+    }
+
+    /// Gets the value of **key**, `None` when it's not set.
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        Ok(match key {
+            "aws.region" => self.aws.region.clone(),
+            "aws.profile" => self.aws.profile.clone(),
+            "aws.endpoint_url" => self.aws.endpoint_url.clone(),
+            "aws.sts_endpoint_url" => self.aws.sts_endpoint_url.clone(),
+            "session.duration" => self.session.duration.map(|d| d.to_string()),
+            "store.tag_key" => Some(self.store.tag_key.clone()),
+            "store.tag_value" => Some(self.store.tag_value.clone()),
+            "store.kms_key_id" => self.store.kms_key_id.clone(),
+            "generate.length" => self.generate.length.map(|l| l.to_string()),
+            "generate.exclude_chars" => self.generate.exclude_chars.clone(),
+            "generate.source" => self.generate.source.clone(),
+            "generate.policy" => self.generate.policy.clone(),
+            "generate.wordlist" => self.generate.wordlist.clone(),
+            "prompt.backend" => self.prompt.backend.clone(),
+            _ if key.starts_with("policies.") => {
+                let (name, policy_key) = parse_policy_key(key)?;
+                let policy = match self.policies.get(name) {
+                    Some(policy) => policy,
+                    None => return Ok(None),
+                };
+                match policy_key {
+                    "length" => policy.length.map(|l| l.to_string()),
+                    "lowercase" => policy.lowercase.map(|b| b.to_string()),
+                    "uppercase" => policy.uppercase.map(|b| b.to_string()),
+                    "digits" => policy.digits.map(|b| b.to_string()),
+                    "symbols" => policy.symbols.map(|b| b.to_string()),
+                    "require_each_type" => policy.require_each_type.map(|b| b.to_string()),
+                    "exclude_ambiguous" => policy.exclude_ambiguous.map(|b| b.to_string()),
+                    "exclude_chars" => policy.exclude_chars.clone(),
+                    "words" => policy.words.map(|w| w.to_string()),
+                    _ => policy.separator.clone(),
+                }
+            }
+            _ => {
+                let (name, store_key) = parse_store_key(key)?;
+                let store = match self.stores.get(name) {
+                    Some(store) => store,
+                    None => return Ok(None),
+                };
+                match store_key {
+                    "tag_value" => store.tag_value.clone(),
+                    "prefix" => store.prefix.clone(),
+                    _ => store.kms_key_id.clone(),
+                }
+            }
+        })
+    }
+
+    /// Sets **key** to **value**, unsetting it when **value** is `None`, and checks the result. Setting a key of a named
+    /// store that doesn't exist adds it, and unsetting `stores.<name>` removes it.
+    pub fn set(&mut self, key: &str, value: Option<&str>) -> Result<()> {
+        let string = value.map(|v| v.to_string());
+        match key {
+            "aws.region" => self.aws.region = string,
+            "aws.profile" => self.aws.profile = string,
+            "aws.endpoint_url" => self.aws.endpoint_url = string,
+            "aws.sts_endpoint_url" => self.aws.sts_endpoint_url = string,
+            "session.duration" => self.session.duration = value.map(|v| parse_int(key, v)).transpose()?,
+            "store.tag_key" => self.store.tag_key = string.unwrap_or_else(|| StoreConfig::default().tag_key),
+            "store.tag_value" => self.store.tag_value = string.unwrap_or_else(|| StoreConfig::default().tag_value),
+            "store.kms_key_id" => self.store.kms_key_id = string,
+            "generate.length" => self.generate.length = value.map(|v| parse_int(key, v)).transpose()?,
+            "generate.exclude_chars" => self.generate.exclude_chars = string,
+            "generate.source" => self.generate.source = string,
+            "generate.policy" => self.generate.policy = string,
+            "generate.wordlist" => self.generate.wordlist = string,
+            "prompt.backend" => self.prompt.backend = string,
+            _ if key.starts_with("policies.") => match key.strip_prefix("policies.") {
+                Some(name) if value.is_none() && self.policies.contains_key(name) => {
+                    self.policies.remove(name);
+                }
+                _ => {
+                    let (name, policy_key) = parse_policy_key(key)?;
+                    let policy = self.policies.entry(name.to_string()).or_default();
+                    let bool = || value.map(|v| parse_bool(key, v)).transpose();
+                    match policy_key {
+                        "length" => policy.length = value.map(|v| parse_int(key, v)).transpose()?,
+                        "lowercase" => policy.lowercase = bool()?,
+                        "uppercase" => policy.uppercase = bool()?,
+                        "digits" => policy.digits = bool()?,
+                        "symbols" => policy.symbols = bool()?,
+                        "require_each_type" => policy.require_each_type = bool()?,
+                        "exclude_ambiguous" => policy.exclude_ambiguous = bool()?,
+                        "exclude_chars" => policy.exclude_chars = string,
+                        "words" => policy.words = value.map(|v| parse_int(key, v)).transpose()?,
+                        _ => policy.separator = string,
+                    }
+                }
+            },
+            _ => match key.strip_prefix("stores.") {
+                Some(name) if value.is_none() && self.stores.contains_key(name) => {
+                    self.stores.remove(name);
+                }
+                _ => {
+                    let (name, store_key) = parse_store_key(key)?;
+                    let store = self.stores.entry(name.to_string()).or_default();
+                    match store_key {
+                        "tag_value" => store.tag_value = string,
+                        "prefix" => store.prefix = string,
+                        _ => store.kms_key_id = string,
+                    }
+                }
+            },
+        }
+        Ok(self.validate()?)
+    }
+
+    /// The keys that are set with their values.
+    pub fn list(&self) -> Result<Vec<(String, String)>> {
+        let mut entries = Vec::new();
+        for key in KEYS.iter() {
+            if let Some(value) = self.get(key)? {
+                entries.push((key.to_string(), value));
+            }
+        }
+        for name in self.stores.keys() {
+            for store_key in STORE_KEYS.iter() {
+                let key = format!("stores.{}.{}", name, store_key);
+                if let Some(value) = self.get(&key)? {
+                    entries.push((key, value));
+                }
+            }
+        }
+        for name in self.policies.keys() {
+            for policy_key in POLICY_KEYS.iter() {
+                let key = format!("policies.{}.{}", name, policy_key);
+                if let Some(value) = self.get(&key)? {
+                    entries.push((key, value));
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// The policy **name**, configured or built in.
+    pub fn policy(&self, name: &str) -> Result<PolicyConfig, AwsPassError> {
+        self.policies
+            .get(name)
+            .cloned()
+            .or_else(|| builtin_policy(name))
+            .ok_or_else(|| {
+                AwsPassError::InvalidInput(format!(
+                    "Unknown policy {}, expected one of {} or a configured one",
+                    name,
+                    BUILTIN_POLICIES.join(", ")
+                ))
+            })
+    }
+
+    /// Where passwords come from, if set.
+    pub fn generate_source(&self) -> Result<Option<Source>, AwsPassError> {
+        self.generate.source.as_deref().map(Source::from_str).transpose()
+    }
+
+    /// The prompt backend, if one is set.
+    pub fn prompt_backend(&self) -> Result<Option<Backend>, AwsPassError> {
+        self.prompt.backend.as_deref().map(Backend::from_str).transpose()
+    }
+
+    fn validate(&self) -> Result<(), AwsPassError> {
+        if let Some(region) = &self.aws.region {
+            // Custom endpoints take any region name, e.g. LocalStack's
+            if self.aws.endpoint_url.is_none()
+                && self.aws.sts_endpoint_url.is_none()
+                && Region::from_str(region).is_err()
+            {
+                return Err(AwsPassError::InvalidInput(format!("Unknown region {}", region)));
+            }
+        }
+        for (key, value) in &[
+            ("aws.profile", &self.aws.profile),
+            ("aws.endpoint_url", &self.aws.endpoint_url),
+            ("aws.sts_endpoint_url", &self.aws.sts_endpoint_url),
+            ("store.kms_key_id", &self.store.kms_key_id),
+        ] {
+            if value.as_deref() == Some("") {
+                return Err(AwsPassError::InvalidInput(format!("{} must be non-empty", key)));
+            }
+        }
+        if let Some(duration) = self.session.duration {
+            if !(MIN_SESSION_DURATION..=MAX_SESSION_DURATION).contains(&duration) {
+                return Err(AwsPassError::InvalidInput(format!(
+                    "session.duration must be between {} and {} seconds",
+                    MIN_SESSION_DURATION, MAX_SESSION_DURATION
+                )));
+            }
+        }
+        if self.store.tag_key.is_empty() || self.store.tag_value.is_empty() {
+            return Err(AwsPassError::InvalidInput(
+                "store.tag_key and store.tag_value must be non-empty".to_string(),
+            ));
+        }
+        if let Some(length) = self.generate.length {
+            if !(1..=MAX_LENGTH).contains(&length) {
+                return Err(AwsPassError::InvalidInput(format!(
+                    "generate.length must be between 1 and {}",
+                    MAX_LENGTH
+                )));
+            }
+        }
+        self.prompt_backend()?;
+        self.generate_source()?;
+        if let Some(policy) = &self.generate.policy {
+            self.policy(policy)?;
+        }
+        if self.generate.wordlist.as_deref() == Some("") {
+            return Err(AwsPassError::InvalidInput(
+                "generate.wordlist must be non-empty".to_string(),
+            ));
+        }
+        for (name, policy) in &self.policies {
+            if name.is_empty() || name.contains('.') {
+                return Err(AwsPassError::InvalidInput(format!(
+                    "Policy name {:?} contains a `.`",
+                    name
+                )));
+            }
+            if policy.length.is_some_and(|l| !(1..=MAX_LENGTH).contains(&l)) {
+                return Err(AwsPassError::InvalidInput(format!(
+                    "policies.{}.length must be between 1 and {}",
+                    name, MAX_LENGTH
+                )));
+            }
+            if policy.words.is_some_and(|w| !(1..=MAX_WORDS).contains(&w)) {
+                return Err(AwsPassError::InvalidInput(format!(
+                    "policies.{}.words must be between 1 and {}",
+                    name, MAX_WORDS
+                )));
+            }
+        }
+        for (name, store) in &self.stores {
+            if name == DEFAULT_STORE || name.is_empty() || name.contains('.') {
+                return Err(AwsPassError::InvalidInput(format!(
+                    "Store name {:?} is reserved or contains a `.`",
+                    name
+                )));
+            }
+            if store.tag_value.is_none() {
+                return Err(AwsPassError::InvalidInput(format!(
+                    "stores.{}.tag_value must be set, before any other key of a new store",
+                    name
+                )));
+            }
+            for (key, value) in &[
+                ("tag_value", &store.tag_value),
+                ("prefix", &store.prefix),
+                ("kms_key_id", &store.kms_key_id),
+            ] {
+                if value.as_deref() == Some("") {
+                    return Err(AwsPassError::InvalidInput(format!(
+                        "stores.{}.{} must be non-empty",
+                        name, key
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits a `stores.<name>.<key>` **key** into the store's name and key.
+fn parse_store_key(key: &str) -> Result<(&str, &str), AwsPassError> {
+    key.strip_prefix("stores.")
+        .and_then(|rest| rest.rsplit_once('.'))
+        .filter(|(_, store_key)| STORE_KEYS.contains(store_key))
+        .ok_or_else(|| unknown_key(key))
+}
+
+/// Splits a `policies.<name>.<key>` **key** into the policy's name and key.
+fn parse_policy_key(key: &str) -> Result<(&str, &str), AwsPassError> {
+    key.strip_prefix("policies.")
+        .and_then(|rest| rest.rsplit_once('.'))
+        .filter(|(_, policy_key)| POLICY_KEYS.contains(policy_key))
+        .ok_or_else(|| unknown_key(key))
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, AwsPassError> {
+    value
+        .parse()
+        .map_err(|_| AwsPassError::InvalidInput(format!("{} must be true or false", key)))
+}
+
+fn parse_int(key: &str, value: &str) -> Result<i64, AwsPassError> {
+    value
+        .parse()
+        .map_err(|_| AwsPassError::InvalidInput(format!("{} must be a number", key)))
+}
+
+fn unknown_key(key: &str) -> AwsPassError {
+    AwsPassError::InvalidInput(format!(
+        "Unknown config key {}, expected one of {}, stores.<name>.{{{}}} or policies.<name>.{{{}}}",
+        key,
+        KEYS.join(", "),
+        STORE_KEYS.join(","),
+        POLICY_KEYS.join(",")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn saved(dir: &TempDir) -> Config {
+        toml::from_str(&fs::read_to_string(dir.path().join(CONFIG_FILENAME)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn migrates_a_store_without_a_config() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(CREDENTIALS_FILENAME), "").unwrap();
+        let config = Config::load(dir.path()).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.aws.region.as_deref(), Some(LEGACY_REGION));
+        assert_eq!(saved(&dir).aws.region.as_deref(), Some(LEGACY_REGION));
+    }
+
+    #[test]
+    fn leaves_an_uninitialized_store_alone() {
+        let dir = TempDir::new().unwrap();
+        let config = Config::load(dir.path()).unwrap();
+        assert_eq!((config.version, config.aws.region), (CONFIG_VERSION, None));
+        assert!(!dir.path().join(CONFIG_FILENAME).exists());
+    }
+
+    #[test]
+    fn migrates_a_version_0_config() {
+        let dir = TempDir::new().unwrap();
+        let contents = "[aws]\nregion = \"eu-west-1\"\n\n[generate]\nlength = 20\n";
+        fs::write(dir.path().join(CONFIG_FILENAME), contents).unwrap();
+        let config = Config::load(dir.path()).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        let saved = saved(&dir);
+        assert_eq!(saved.version, CONFIG_VERSION);
+        assert_eq!(saved.aws.region.as_deref(), Some("eu-west-1"));
+        assert_eq!(saved.generate.length, Some(20));
+    }
+
+    #[test]
+    fn rejects_newer_and_invalid_configs() {
+        let dir = TempDir::new().unwrap();
+        for contents in [
+            "version = 2\n",
+            "version = 1\n[aws]\nregion = \"mars-1\"\n",
+            "version = 1\n[aws]\nunknown = 1\n",
+            "version = 1\n[stores.team]\nprefix = \"team/\"\n",
+        ] {
+            fs::write(dir.path().join(CONFIG_FILENAME), contents).unwrap();
+            assert!(Config::load(dir.path()).is_err(), "{}", contents);
+            assert_eq!(fs::read_to_string(dir.path().join(CONFIG_FILENAME)).unwrap(), contents);
+        }
+    }
+
+    #[test]
+    fn sets_gets_and_unsets() {
+        let dir = TempDir::new().unwrap();
+        let mut config = Config::new();
+        for (key, value) in [
+            ("aws.region", "eu-west-1"),
+            ("session.duration", "3600"),
+            ("generate.length", "24"),
+            ("policies.web.symbols", "false"),
+            ("stores.team.tag_value", "team"),
+            ("stores.team.prefix", "team/"),
+        ] {
+            config.set(key, Some(value)).unwrap();
+            assert_eq!(config.get(key).unwrap().as_deref(), Some(value), "{}", key);
+        }
+        config.save(dir.path()).unwrap();
+        let loaded = Config::load(dir.path()).unwrap();
+        assert_eq!(loaded.list().unwrap(), config.list().unwrap());
+        config.set("generate.length", None).unwrap();
+        assert_eq!(config.get("generate.length").unwrap(), None);
+        config.set("store.tag_value", None).unwrap();
+        assert_eq!(config.get("store.tag_value").unwrap().as_deref(), Some("true"));
+        config.set("stores.team", None).unwrap();
+        assert_eq!(config.get("stores.team.prefix").unwrap(), None);
+        config.set("policies.web", None).unwrap();
+        assert!(config.policies.is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        for (key, value) in [
+            ("aws.region", "mars-1"),
+            ("aws.profile", ""),
+            ("session.duration", "60"),
+            ("session.duration", "soon"),
+            ("generate.length", "0"),
+            ("generate.length", "4097"),
+            ("generate.source", "dice"),
+            ("policies.web.words", "65"),
+            ("policies.web.symbols", "maybe"),
+            ("policies.a.b.length", "8"),
+            ("stores.default.tag_value", "x"),
+            ("stores.team.tag_value", ""),
+            ("prompt.backend", "carrier-pigeon"),
+            ("no.such_key", "x"),
+        ] {
+            assert!(Config::new().set(key, Some(value)).is_err(), "{} = {}", key, value);
+        }
+    }
+
+    #[test]
+    fn sets_a_new_store_tag_value_first() {
+        let mut config = Config::new();
+        let err = config.set("stores.team.prefix", Some("team/")).unwrap_err();
+        assert!(err.to_string().contains("stores.team.tag_value must be set"), "{}", err);
+        config.set("stores.team.tag_value", Some("team")).unwrap();
+        config.set("stores.team.prefix", Some("team/")).unwrap();
+        assert!(config.set("stores.team.tag_value", None).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+pub mod config;
+
+use crate::{creds::MIN_SESSION_DURATION, error::AwsPassError, output};
+use anyhow::Result;
+use config::{Config, DEFAULT_STORE};
+use rusoto_core::Region;
+use serde_json::{json, Value};
+use std::{env, path::Path, str::FromStr};
+
+const DEFAULT_REGION: &str = "us-east-1";
+const DEFAULT_PROFILE: &str = "default";
+
+/// The settings a store is opened with, resolved from flags, the environment and the store's config.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub region: String,
+    pub profile: String,
+    /// The region for Secrets Manager, a custom region when an endpoint is set, e.g. for a VPC endpoint or LocalStack.
+    pub sm_region: Region,
+    /// The region for STS, a custom region when an endpoint is set.
+    pub sts_region: Region,
+    pub sm_endpoint: Option<String>,
+    pub sts_endpoint: Option<String>,
+    pub session_duration: i64,
+    /// The store selected with `--store`.
+    pub store: Store,
+    pub config: Config,
+}
+
+/// A store within the account, the secrets tagged with its tag and named with its prefix.
+#[derive(Debug, Clone)]
+pub struct Store {
+    pub name: String,
+    /// The tag marking the store's secrets.
+    pub tag: (String, String),
+    /// The prefix of the store's secret names, empty when the store has none.
+    pub prefix: String,
+    pub kms_key_id: Option<String>,
+}
+
+impl Store {
+    /// The store named **name** as configured in **config**.
+    pub fn from_config(config: &Config, name: &str) -> Result<Store> {
+        if name == DEFAULT_STORE {
+            return Ok(Store {
+                name: name.to_string(),
+                tag: (config.store.tag_key.clone(), config.store.tag_value.clone()),
+                prefix: String::new(),
+                kms_key_id: config.store.kms_key_id.clone(),
+            });
+        }
+        let store = config
+            .stores
+            .get(name)
+            .ok_or_else(|| AwsPassError::InvalidInput(format!("Unknown store {}, see `aws-pass stores list`", name)))?;
+        Ok(Store {
+            name: name.to_string(),
+            tag: (
+                config.store.tag_key.clone(),
+                store.tag_value.clone().unwrap_or_default(),
+            ),
+            prefix: store.prefix.clone().unwrap_or_default(),
+            kms_key_id: store.kms_key_id.clone().or_else(|| config.store.kms_key_id.clone()),
+        })
+    }
+}
+
+/// The settings given on the command line, where the flags also read their `AWS_PASS_` environment variables.
+#[derive(Debug, Clone, Default)]
+pub struct SettingsArgs {
+    pub region: Option<String>,
+    pub profile: Option<String>,
+    pub sm_endpoint: Option<String>,
+    pub sts_endpoint: Option<String>,
+    pub session_duration: Option<i64>,
+    pub store: Option<String>,
+}
+
+impl Settings {
+    /// Resolves the settings for the store at **store_dir**, flags and their environment variables take precedence over
+    /// the store's config. The region of a store yet to be initialized falls back to `AWS_REGION`.
+    pub fn resolve(store_dir: &Path, args: SettingsArgs) -> Result<Settings> {
+        let config = Config::load(store_dir)?;
+        // Stores that didn't record their region were created in the default one, `AWS_REGION` only picks the region
+        // of stores yet to be initialized
+        let initialized = store_dir.join(config::CREDENTIALS_FILENAME).exists();
+        let region = args
+            .region
+            .or_else(|| config.aws.region.clone())
+            .or_else(|| env::var("AWS_REGION").ok().filter(|_| !initialized))
+            .unwrap_or_else(|| DEFAULT_REGION.to_string());
+        let sm_endpoint = args.sm_endpoint.or_else(|| config.aws.endpoint_url.clone());
+        let sts_endpoint = args.sts_endpoint.or_else(|| config.aws.sts_endpoint_url.clone());
+        Ok(Settings {
+            sm_region: to_region(&region, sm_endpoint.as_deref())?,
+            sts_region: to_region(&region, sts_endpoint.as_deref())?,
+            region,
+            profile: args
+                .profile
+                .or_else(|| config.aws.profile.clone())
+                .unwrap_or_else(|| DEFAULT_PROFILE.to_string()),
+            sm_endpoint,
+            sts_endpoint,
+            session_duration: args
+                .session_duration
+                .or(config.session.duration)
+                .unwrap_or(MIN_SESSION_DURATION),
+            store: Store::from_config(&config, args.store.as_deref().unwrap_or(DEFAULT_STORE))?,
+            config,
+        })
+    }
+
+    /// The config to record for a new store, the store's config with the resolved region, profile and endpoints.
+    pub fn init_config(&self) -> Config {
+        let mut config = self.config.clone();
+        config.aws.region = Some(self.region.clone());
+        config.aws.profile = Some(self.profile.clone()).filter(|p| p != DEFAULT_PROFILE);
+        config.aws.endpoint_url = self.sm_endpoint.clone();
+        config.aws.sts_endpoint_url = self.sts_endpoint.clone();
+        config
+    }
+}
+
+fn to_region(region: &str, endpoint: Option<&str>) -> Result<Region> {
+    match endpoint {
+        Some(endpoint) => Ok(Region::Custom {
+            name: region.to_string(),
+            endpoint: endpoint.to_string(),
+        }),
+        None => Region::from_str(region)
+            .map_err(|_| AwsPassError::InvalidInput(format!("Unknown region {}", region)).into()),
+    }
+}
+
+/// Prints the value of the config **key** of the store at **store_dir**.
+pub fn get(store_dir: &Path, key: &str) -> Result<()> {
+    match Config::load(store_dir)?.get(key)? {
+        Some(value) if !output::is_text() => output::print(&json!({"key": key, "value": value}))?,
+        Some(value) => println!("{}", value),
+        None => return Err(AwsPassError::NotFound(format!("Config key {}", key)).into()),
+    }
+    Ok(())
+}
+
+/// Sets the config **key** of the store at **store_dir** to **value**, unsetting it when **value** is `None`.
+pub fn set(store_dir: &Path, key: &str, value: Option<&str>) -> Result<()> {
+    let mut config = Config::load(store_dir)?;
+    if !store_dir.join(config::CONFIG_FILENAME).exists() {
+        return Err(AwsPassError::InvalidInput(format!(
+            "Store dir {} not initialized, run `aws-pass init`",
+            store_dir.display()
+        ))
+        .into());
+    }
+    config.set(key, value)?;
+    config.save(store_dir)
+}
+
+/// Prints the config keys of the store at **store_dir** that are set, with their values.
+pub fn list(store_dir: &Path) -> Result<()> {
+    let entries = Config::load(store_dir)?.list()?;
+    if !output::is_text() {
+        return output::print(&json!(entries
+            .iter()
+            .map(|(key, value)| json!({"key": key, "value": value}))
+            .collect::<Vec<Value>>()));
+    }
+    for (key, value) in entries {
+        println!("{} = {}", key, value);
+    }
+    Ok(())
+}
+
+/// Prints the stores configured for the store dir at **store_dir**, marking the **selected** one.
+pub fn list_stores(store_dir: &Path, selected: Option<&str>) -> Result<()> {
+    let config = Config::load(store_dir)?;
+    let selected = selected.unwrap_or(DEFAULT_STORE);
+    let names = std::iter::once(DEFAULT_STORE).chain(config.stores.keys().map(|n| n.as_str()));
+    let stores = names
+        .map(|name| Store::from_config(&config, name))
+        .collect::<Result<Vec<Store>>>()?;
+    if !output::is_text() {
+        return output::print(&json!(stores
+            .iter()
+            .map(|s| json!({
+                "name": s.name,
+                "tag_key": s.tag.0,
+                "tag_value": s.tag.1,
+                "prefix": s.prefix,
+                "kms_key_id": s.kms_key_id,
+                "selected": s.name == selected,
+            }))
+            .collect::<Vec<Value>>()));
+    }
+    for store in stores {
+        let name = store.name.as_str();
+        let (key, value) = &store.tag;
+        let prefix = match store.prefix.as_str() {
+            "" => String::new(),
+            prefix => format!(", prefix {}", prefix),
+        };
+        let marker = if name == selected { "*" } else { " " };
+        println!("{} {} (tag {}={}{})", marker, name, key, value, prefix);
+    }
+    Ok(())
+}
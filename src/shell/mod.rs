@@ -0,0 +1,126 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rustyline::{
+    completion::Completer,
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::Hinter,
+    history::{DefaultHistory, History},
+    validate::Validator,
+    Config, Context, Editor, Helper,
+};
+use std::{fs, os::unix::fs::PermissionsExt, path::PathBuf};
+
+/// Line editing for `aws-pass shell`, with tab completion of commands and password names and a history kept in a
+/// private file. Lines that may hold a secret value, and lines starting with a space, are kept out of the history.
+pub struct Shell {
+    editor: Editor<ShellHelper, DefaultHistory>,
+    history_path: PathBuf,
+}
+
+impl Shell {
+    /// Opens the shell with the history at **history_path**, completing the given **commands** as first words.
+    pub fn new(history_path: PathBuf, commands: &[&str]) -> Result<Shell> {
+        let config = Config::builder().auto_add_history(false).build();
+        let mut editor = Editor::with_config(config)?;
+        editor.set_helper(Some(ShellHelper {
+            commands: commands.iter().map(|c| c.to_string()).collect(),
+            names: Vec::new(),
+        }));
+        if history_path.exists() {
+            editor.load_history(&history_path)?;
+        }
+        Ok(Shell { editor, history_path })
+    }
+
+    /// Replaces the password names completed with **names**.
+    pub fn set_names(&mut self, names: Vec<String>) {
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.names = names;
+        }
+    }
+
+    /// Reads the next line split into words as a POSIX shell would, none at the end of input. Interrupted lines and
+    /// lines that don't split are dropped.
+    pub fn read_line(&mut self, prompt: &str) -> Result<Option<Vec<String>>> {
+        loop {
+            let line = match self.editor.readline(prompt) {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+            let words = match shell_words::split(&line) {
+                Ok(words) if words.is_empty() => continue,
+                Ok(words) => words,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    continue;
+                }
+            };
+            if !line.starts_with(' ') && !holds_secret(&words) {
+                self.editor.add_history_entry(line.trim())?;
+                self.save_history()?;
+            }
+            return Ok(Some(words));
+        }
+    }
+
+    fn save_history(&mut self) -> Result<()> {
+        if self.editor.history().is_empty() {
+            return Ok(());
+        }
+        self.editor.save_history(&self.history_path)?;
+        fs::set_permissions(&self.history_path, fs::Permissions::from_mode(0o600))?;
+        Ok(())
+    }
+}
+
+/// The shell's prompt, showing how long the session **expires_at** has left.
+pub fn prompt(expires_at: Option<DateTime<Utc>>) -> String {
+    let left = match expires_at.map(|e| (e - Utc::now()).num_minutes()) {
+        None => return "aws-pass> ".to_string(),
+        Some(m) if m < 0 => "expired".to_string(),
+        Some(0) => "<1m left".to_string(),
+        Some(m) if m < 60 => format!("{}m left", m),
+        Some(m) => format!("{}h{:02}m left", m / 60, m % 60),
+    };
+    format!("aws-pass ({})> ", left)
+}
+
+/// Whether the line of **words** may hold a secret value, i.e. inserts a field given with its value.
+fn holds_secret(words: &[String]) -> bool {
+    words.first().map(String::as_str) == Some("insert") && words.iter().skip(1).any(|w| w.contains('='))
+}
+
+struct ShellHelper {
+    commands: Vec<String>,
+    names: Vec<String>,
+}
+
+impl Completer for ShellHelper {
+    type Candidate = String;
+
+    /// Completes the first word with the commands and any other word with the password names.
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = &line[start..pos];
+        let candidates = match line[..start].trim().is_empty() {
+            true => &self.commands,
+            false => &self.names,
+        };
+        let mut matches: Vec<String> = candidates.iter().filter(|c| c.starts_with(word)).cloned().collect();
+        matches.sort();
+        Ok((start, matches))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
@@ -1,25 +1,85 @@
-use super::pass_store::PassStore;
+use super::{
+    listing_cache::{CachedPassword, ListingCache},
+    pass_store::{GenerateOptions, InsertMode, PassStore, RemoveOptions, Selector},
+    trash::{Trash, TrashRecord},
+};
 use crate::{
-    creds::StsLocalMfaCredsProvider,
+    agent::AGENT_SOCKET_FILENAME,
+    clipboard,
+    creds::{
+        self, mfa_seed::MfaSeed, AgentCredsProvider, LocalMfaFiles, SharedCredsProvider, StsLocalMfaCredsProvider,
+    },
     dao::{
         pass_dao::Tag,
-        pass_dao::{Filter, PassDao, Password},
-        sm_pass_dao::SmPassDao,
+        pass_dao::{Filter, PassDao, Password, PasswordDetails, PasswordVersion},
+        sm_pass_dao::{SmPassDao, CURRENT_STAGE},
     },
-    util,
+    diff::{self, Change},
+    entry::Entry,
+    error::{self, AwsPassError},
+    fuzzy,
+    generator::{Policy, Source},
+    otp::{self, Algorithm, OtpKind, OtpUri},
+    output,
+    picker::{self, Action},
+    prompt,
+    settings::{
+        config::{PolicyConfig, MAX_LENGTH, MAX_WORDS},
+        Settings,
+    },
+    tree::Tree,
+    util::{self, BLUE_BOLD, RED_BOLD, RESET},
 };
 use anyhow::Result;
 use async_trait::async_trait;
-use rusoto_core::Region;
-use std::fs;
-use std::{path::PathBuf, process};
-use util::write_lines;
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt};
+use log::info;
+use regex::{Captures, Regex};
+use rusoto_core::{
+    credential::{AwsCredentials, ProvideAwsCredentials},
+    RusotoError,
+};
+use serde_json::{json, Map, Value};
+use std::path::PathBuf;
+use std::{
+    convert::Infallible,
+    fs::{self, Permissions},
+    os::unix::fs::PermissionsExt,
+};
+use util::write_private_file;
 
 const CREDENTIALS_FILENAME: &str = ".credentials";
 const TOKEN_SERIAL_FILENAME: &str = ".token-serial";
-// TODO: Fix tags
-const STORE_TAGS: (&str, &str) = ("aws-pass", "true");
-const STORE_FILTERS: [(&str, [&str; 1]); 2] = [("tag-key", ["aws-pass"]), ("tag-value", ["true"])];
+const SESSION_CACHE_FILENAME: &str = ".session";
+const MFA_SEED_FILENAME: &str = ".mfa-seed";
+const MFA_LAST_STEP_FILENAME: &str = ".mfa-last-step";
+const TRASH_FILENAME: &str = ".trash";
+const LISTING_CACHE_FILENAME: &str = ".listing-cache";
+/// The most requests sent at once when working on many passwords, well below Secrets Manager's rate limits.
+const MAX_CONCURRENT_REQUESTS: usize = 8;
+
+/// A password `mv` has written to its destination, with what undoes the write should the move fail.
+struct Moved {
+    source_id: String,
+    undo: Undo,
+}
+
+enum Undo {
+    /// Delete the created destination, without a recovery window so that its name is free again.
+    Delete(String),
+    /// Put the overwritten destination's previous value back.
+    Restore(String, String),
+}
+
+impl Undo {
+    /// The id of the destination password.
+    fn id(&self) -> &str {
+        match self {
+            Undo::Delete(id) | Undo::Restore(id, _) => id,
+        }
+    }
+}
 
 struct StoreDetails {
     access_key_id: String,
@@ -29,44 +89,86 @@ struct StoreDetails {
 
 pub struct DefaultPassStore {
     store_dir: PathBuf,
+    settings: Settings,
+    creds_provider: SharedCredsProvider,
     pass_dao: Box<dyn PassDao + Send + Sync>,
 }
 
 impl DefaultPassStore {
-    pub fn new(store_dir: PathBuf, region: &Region) -> Box<dyn PassStore> {
-        let creds_provider = StsLocalMfaCredsProvider::new(
+    pub fn new(store_dir: PathBuf, settings: &Settings) -> Box<dyn PassStore> {
+        let creds_provider = SharedCredsProvider::new(AgentCredsProvider::new(
+            store_dir.join(AGENT_SOCKET_FILENAME),
             store_dir.join(CREDENTIALS_FILENAME),
-            store_dir.join(TOKEN_SERIAL_FILENAME),
-            region,
-        );
-        Box::new(DefaultPassStore {
+            &settings.profile,
+            settings.sts_endpoint.as_deref(),
+            StsLocalMfaCredsProvider::new(
+                LocalMfaFiles {
+                    creds_path: store_dir.join(CREDENTIALS_FILENAME),
+                    token_serial_path: store_dir.join(TOKEN_SERIAL_FILENAME),
+                    session_cache_path: store_dir.join(SESSION_CACHE_FILENAME),
+                    mfa_seed_path: store_dir.join(MFA_SEED_FILENAME),
+                    mfa_last_step_path: store_dir.join(MFA_LAST_STEP_FILENAME),
+                },
+                &settings.profile,
+                settings.sts_endpoint.as_deref(),
+                settings.session_duration,
+                &settings.sts_region,
+            ),
+        ));
+        Box::new(DefaultPassStore::with_creds(store_dir, settings, creds_provider))
+    }
+
+    /// Opens the store with the given **creds_provider**, e.g. the provider of another store so that both share one
+    /// MFA session.
+    fn with_creds(store_dir: PathBuf, settings: &Settings, creds_provider: SharedCredsProvider) -> DefaultPassStore {
+        DefaultPassStore {
             store_dir,
-            pass_dao: Box::new(SmPassDao::new(creds_provider, region)),
-        })
+            settings: settings.clone(),
+            pass_dao: Box::new(SmPassDao::new(creds_provider.clone(), &settings.sm_region)),
+            creds_provider,
+        }
     }
 
-    fn ensure_empty_store_dir(&self) {
-        if self.store_dir.exists() && self.store_dir.is_dir() && self.store_dir.read_dir().unwrap().next().is_some() {
-            fatal_println!("Store dir {} not empty, not overwriting", self.store_dir.display())
+    fn ensure_empty_store_dir(&self) -> Result<()> {
+        if self.store_dir.exists() && self.store_dir.is_dir() && self.store_dir.read_dir()?.next().is_some() {
+            return Err(AwsPassError::InvalidInput(format!(
+                "Store dir {} not empty, not overwriting",
+                self.store_dir.display()
+            ))
+            .into());
         }
         if self.store_dir.exists() && self.store_dir.is_file() {
-            fatal_println!("Store dir {} not a directory", self.store_dir.display())
+            return Err(
+                AwsPassError::InvalidInput(format!("Store dir {} not a directory", self.store_dir.display())).into(),
+            );
         }
         if !self.store_dir.exists() {
             println!("Creating store dir at {}", self.store_dir.display());
-            fs::create_dir(&self.store_dir).unwrap();
+            fs::create_dir(&self.store_dir)?;
         }
+        Ok(())
     }
 
-    fn get_store_details(&self) -> StoreDetails {
-        let access_key_id = util::prompt_non_empty_str("AWS Access Key Id");
-        let secret_access_key = util::prompt_non_empty_str("AWS Secret Access Key");
-        let token_serial = util::prompt_non_empty_str("MFA Token Serial Number");
-        StoreDetails {
+    fn ensure_initialized(&self) -> Result<()> {
+        if !self.store_dir.join(CREDENTIALS_FILENAME).exists() || !self.store_dir.join(TOKEN_SERIAL_FILENAME).exists() {
+            return Err(AwsPassError::InvalidInput(format!(
+                "Store dir {} not initialized, run `aws-pass init`",
+                self.store_dir.display()
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    fn get_store_details(&self) -> Result<StoreDetails> {
+        let access_key_id = prompt::ask_non_empty("aws-access-key-id", "AWS Access Key Id", false)?;
+        let secret_access_key = prompt::ask_non_empty("aws-secret-access-key", "AWS Secret Access Key", true)?;
+        let token_serial = prompt::ask_non_empty("mfa-token-serial", "MFA Token Serial Number", false)?;
+        Ok(StoreDetails {
             access_key_id,
             secret_access_key,
             token_serial,
-        }
+        })
     }
 
     fn write_store_details(
@@ -76,97 +178,1412 @@ impl DefaultPassStore {
             secret_access_key,
             token_serial,
         }: &StoreDetails,
-    ) {
+    ) -> Result<()> {
         let creds_path = self.store_dir.join(CREDENTIALS_FILENAME);
-        write_lines(
-            &creds_path,
-            vec![
-                "[default]\n",
-                format!("aws_access_key_id={}\n", access_key_id).as_ref(),
-                format!("aws_secret_access_key={}\n", secret_access_key).as_ref(),
-            ],
+        let creds = format!(
+            "[{}]\naws_access_key_id={}\naws_secret_access_key={}\n",
+            self.settings.profile, access_key_id, secret_access_key
         );
+        write_private_file(&creds_path, creds.as_bytes())?;
 
         let token_serial_path = self.store_dir.join(TOKEN_SERIAL_FILENAME);
-        write_lines(&token_serial_path, vec![token_serial.as_ref()]);
+        write_private_file(&token_serial_path, token_serial.as_bytes())?;
+
+        self.settings.init_config().save(&self.store_dir)
     }
 
-    async fn get_password_by_name(&self, name: &str) -> Result<Password> {
-        let filters: Vec<Filter> = STORE_FILTERS
-            .iter()
-            .map(|f| (f.0.to_string(), f.1.iter().map(|s| s.to_string()).collect()))
+    /// Edits a JSON entry as **yaml**, re-opening the editor for as long as the result isn't a valid entry and the user
+    /// wants to fix it.
+    fn edit_yaml(&self, yaml: String) -> Result<Entry> {
+        let mut yaml = yaml;
+        loop {
+            yaml = edit::edit(yaml)?;
+            match Entry::from_yaml(&yaml) {
+                Ok(entry) => return Ok(entry),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    let answer = prompt::ask("edit-again", "Edit again? [Y/n]")?;
+                    if answer.eq_ignore_ascii_case("n") {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn mfa_seed(&self) -> Result<MfaSeed> {
+        self.ensure_initialized()?;
+        Ok(MfaSeed::new(
+            self.store_dir.join(MFA_SEED_FILENAME),
+            self.store_dir.join(MFA_LAST_STEP_FILENAME),
+            &creds::read_secret_access_key(&self.store_dir.join(CREDENTIALS_FILENAME), &self.settings.profile).await?,
+            &creds::read_token_serial(&self.store_dir.join(TOKEN_SERIAL_FILENAME))?,
+        ))
+    }
+
+    /// The tags marking secrets as part of the store.
+    fn store_tags(&self) -> Vec<Tag> {
+        vec![self.settings.store.tag.clone()]
+    }
+
+    /// The filters scoping secret listings to the store's secrets whose names start with **prefix**.
+    fn store_filters(&self, prefix: Option<&str>) -> Vec<Filter> {
+        let store = &self.settings.store;
+        let mut filters = Vec::new();
+        let (key, value) = &store.tag;
+        filters.push(("tag-key".to_string(), vec![key.clone()]));
+        filters.push(("tag-value".to_string(), vec![value.clone()]));
+        let name_prefix = format!("{}{}", store.prefix, prefix.unwrap_or_default());
+        if !name_prefix.is_empty() {
+            filters.push(("name".to_string(), vec![name_prefix]));
+        }
+        filters
+    }
+
+    fn trash(&self) -> Trash {
+        Trash::new(self.store_dir.join(TRASH_FILENAME))
+    }
+
+    /// Whether **record** is of a password removed from this store.
+    fn is_own_record(&self, record: &TrashRecord) -> bool {
+        record.store == self.settings.store.name && record.region == self.settings.region
+    }
+
+    /// The cached listing of the store for the current session, whose credentials are obtained if needed.
+    async fn listing_cache(&self) -> Result<ListingCache> {
+        let creds = self.session_creds().await?;
+        Ok(ListingCache::new(
+            self.store_dir.join(LISTING_CACHE_FILENAME),
+            creds.aws_secret_access_key(),
+            &self.settings.store.name,
+            &self.settings.region,
+            *creds.expires_at(),
+        ))
+    }
+
+    /// The store's listing cached for the session, listed again and cached when missing or when **refresh** is set.
+    async fn cached_listing(&self, refresh: bool) -> Result<Vec<CachedPassword>> {
+        let cache = self.listing_cache().await?;
+        if let Some(passwords) = cache.load().filter(|_| !refresh) {
+            return Ok(passwords);
+        }
+        let passwords: Vec<CachedPassword> = self
+            .list_passwords(None)
+            .await?
+            .into_iter()
+            .map(CachedPassword::from)
             .collect();
-        self.pass_dao.get_password_by_name(name, Some(&filters)).await
+        cache.store(&passwords)?;
+        Ok(passwords)
+    }
+
+    /// Obtains the session's credentials, asking for an MFA token code when there's no session yet.
+    async fn session_creds(&self) -> Result<AwsCredentials> {
+        self.creds_provider
+            .credentials()
+            .await
+            .map_err(|e| error::from_rusoto(RusotoError::<Infallible>::Credentials(e), "the session"))
+    }
+
+    /// Drops the cached listing, before the store's passwords change.
+    fn forget_listing(&self) {
+        let path = self.store_dir.join(LISTING_CACHE_FILENAME);
+        if path.exists() {
+            if let Err(e) = fs::remove_file(&path) {
+                info!("Could not remove the listing cache: {}", e);
+            }
+        }
+    }
+
+    /// The policy **options** ask for: the config's defaults, overridden by the chosen policy, overridden by the
+    /// options themselves.
+    fn generate_policy(&self, options: &GenerateOptions) -> Result<Policy> {
+        let config = &self.settings.config;
+        if options.length.is_some_and(|l| !(1..=MAX_LENGTH as usize).contains(&l)) {
+            return Err(AwsPassError::InvalidInput(format!("--length must be between 1 and {}", MAX_LENGTH)).into());
+        }
+        if options.words.is_some_and(|w| !(1..=MAX_WORDS as usize).contains(&w)) {
+            return Err(AwsPassError::InvalidInput(format!("--words must be between 1 and {}", MAX_WORDS)).into());
+        }
+        let mut policy = Policy::default();
+        policy.apply(&PolicyConfig {
+            length: config.generate.length,
+            exclude_chars: config.generate.exclude_chars.clone(),
+            ..Default::default()
+        });
+        if let Some(name) = options.policy.as_ref().or(config.generate.policy.as_ref()) {
+            policy.apply(&config.policy(name)?);
+        }
+        policy.apply(&PolicyConfig {
+            length: options.length.map(|l| l as i64),
+            lowercase: Some(false).filter(|_| options.no_lowercase),
+            uppercase: Some(false).filter(|_| options.no_uppercase),
+            digits: Some(false).filter(|_| options.no_digits),
+            symbols: Some(false).filter(|_| options.no_symbols),
+            require_each_type: Some(true).filter(|_| options.require_each_type),
+            exclude_ambiguous: Some(true).filter(|_| options.no_ambiguous),
+            exclude_chars: options.exclude_chars.clone(),
+            words: options.words.map(|w| w as i64),
+            separator: options.separator.clone(),
+        });
+        Ok(policy)
+    }
+
+    /// The name of the secret holding the store's password **name**.
+    fn secret_name(&self, name: &str) -> String {
+        format!("{}{}", self.settings.store.prefix, name)
+    }
+
+    /// Lists the store's passwords whose names start with **prefix**, named as the store shows them.
+    async fn list_passwords(&self, prefix: Option<&str>) -> Result<Vec<PasswordDetails>> {
+        self.list_filtered(&self.store_filters(prefix)).await
+    }
+
+    /// Lists the store's passwords that Secrets Manager matches for the filter **key** and **value**, named as the
+    /// store shows them. The filter replaces the store's own filter of the same key.
+    async fn search_passwords(&self, key: &str, value: &str) -> Result<Vec<PasswordDetails>> {
+        let mut filters: Vec<Filter> = self.store_filters(None).into_iter().filter(|(k, _)| k != key).collect();
+        filters.push((key.to_string(), vec![value.to_string()]));
+        self.list_filtered(&filters).await
+    }
+
+    /// Lists the secrets matching **filters** that are the store's, named as the store shows them. The tag filters
+    /// match tag keys and values separately, only secrets carrying the store's tag itself are the store's.
+    async fn list_filtered(&self, filters: &[Filter]) -> Result<Vec<PasswordDetails>> {
+        let store = &self.settings.store;
+        let passwords = self.pass_dao.list_passwords(filters).await?;
+        Ok(passwords
+            .into_iter()
+            .filter(|p| p.tags.contains(&store.tag))
+            .filter_map(|p| {
+                let name = p.name.strip_prefix(&store.prefix)?.to_string();
+                Some(PasswordDetails { name, ..p })
+            })
+            .collect())
+    }
+
+    async fn create_password(&self, name: &str, value: &str) -> Result<Password> {
+        self.forget_listing();
+        let tags = self.store_tags();
+        self.pass_dao
+            .create_password(
+                &self.secret_name(name),
+                value,
+                None,
+                Some(&tags),
+                self.settings.store.kms_key_id.as_deref(),
+            )
+            .await
+    }
+
+    /// Finds the store's password **name**, which must match exactly one secret.
+    async fn find_password(&self, name: &str) -> Result<PasswordDetails> {
+        self.ensure_initialized()?;
+        let mut passwords: Vec<PasswordDetails> = self
+            .list_passwords(Some(name))
+            .await?
+            .into_iter()
+            .filter(|p| p.name == name)
+            .collect();
+        match passwords.len() {
+            0 => Err(AwsPassError::NotFound(name.to_string()).into()),
+            1 => Ok(passwords.remove(0)),
+            n => Err(AwsPassError::Ambiguous(name.to_string(), n).into()),
+        }
+    }
+
+    /// Pairs the passwords `mv` and `cp` transfer from **from** with their destinations. A **from** ending in `/`
+    /// transfers every password under it into the folder **to**, and a **to** ending in `/` transfers **from** into
+    /// that folder. Destinations within the **same_store** must differ from their sources.
+    async fn transfer_plan(&self, from: &str, to: &str, same_store: bool) -> Result<Vec<(String, String)>> {
+        let plan: Vec<(String, String)> = if from.ends_with('/') {
+            if !to.ends_with('/') || (same_store && to.starts_with(from)) {
+                return Err(AwsPassError::InvalidInput(format!(
+                    "Transferring the folder {} needs a destination folder ending in / outside of it",
+                    from
+                ))
+                .into());
+            }
+            // The name filter also matches words within names, only the names under the folder are transferred
+            let plan: Vec<(String, String)> = self
+                .list_passwords(Some(from))
+                .await?
+                .into_iter()
+                .filter_map(|p| {
+                    let dst = format!("{}{}", to, p.name.strip_prefix(from)?);
+                    Some((p.name, dst))
+                })
+                .collect();
+            if plan.is_empty() {
+                return Err(AwsPassError::NotFound(from.to_string()).into());
+            }
+            plan
+        } else if to.ends_with('/') {
+            let basename = from.rsplit('/').next().unwrap_or(from);
+            vec![(from.to_string(), format!("{}{}", to, basename))]
+        } else {
+            vec![(from.to_string(), to.to_string())]
+        };
+        if let Some((src, _)) = plan.iter().find(|(src, dst)| same_store && src == dst) {
+            return Err(AwsPassError::InvalidInput(format!("Cannot transfer {} onto itself", src)).into());
+        }
+        Ok(plan)
+    }
+
+    /// Writes **value** to the password **dst** of the **dest** store with the description, tags and KMS key of
+    /// **source**. The source store's tag is swapped for the destination's, and the KMS key only carries over within a
+    /// region. An existing **dst** is only overwritten, as a new version, when **force** is set.
+    async fn write_to(
+        &self,
+        dest: &DefaultPassStore,
+        source: &PasswordDetails,
+        value: &str,
+        dst: &str,
+        force: bool,
+    ) -> Result<Undo> {
+        dest.forget_listing();
+        match dest.find_password(dst).await {
+            Ok(existing) if force => {
+                let previous = dest.pass_dao.get_password(&existing.id).await?.value;
+                dest.pass_dao.update_password(&existing.id, value).await?;
+                Ok(Undo::Restore(existing.id, previous))
+            }
+            Ok(_) => Err(AwsPassError::AlreadyExists(dst.to_string()).into()),
+            Err(e) if matches!(e.downcast_ref(), Some(AwsPassError::NotFound(_))) => {
+                let mut tags: Vec<Tag> = source
+                    .tags
+                    .iter()
+                    .filter(|t| *t != &self.settings.store.tag)
+                    .cloned()
+                    .collect();
+                tags.push(dest.settings.store.tag.clone());
+                let kms_key_id = match self.settings.sm_region == dest.settings.sm_region {
+                    true => source.kms_key_id.as_deref(),
+                    false => dest.settings.store.kms_key_id.as_deref(),
+                };
+                let created = dest
+                    .pass_dao
+                    .create_password(
+                        &dest.secret_name(dst),
+                        value,
+                        source.description.as_deref(),
+                        Some(tags.as_slice()).filter(|t| !t.is_empty()),
+                        kms_key_id,
+                    )
+                    .await?;
+                Ok(Undo::Delete(created.id))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes the password **src** to **dst** and reads it back, see **write_to**.
+    async fn move_to(&self, src: &str, dst: &str, force: bool) -> Result<Moved> {
+        let source = self.find_password(src).await?;
+        let value = self.pass_dao.get_password(&source.id).await?.value;
+        let undo = self.write_to(self, &source, &value, dst, force).await?;
+        let moved = Moved {
+            source_id: source.id,
+            undo,
+        };
+        match self.pass_dao.get_password(moved.undo.id()).await {
+            Ok(written) if written.value == value => Ok(moved),
+            result => {
+                self.undo_moves(vec![moved]).await;
+                let err = result
+                    .err()
+                    .unwrap_or_else(|| anyhow::anyhow!("{} does not read back as written", dst));
+                Err(err)
+            }
+        }
+    }
+
+    /// Undoes the writes of **moved**, reporting the ones that can't be undone.
+    async fn undo_moves(&self, moved: Vec<Moved>) {
+        for Moved { undo, .. } in moved {
+            let result = match &undo {
+                Undo::Delete(id) => self.pass_dao.delete_password(id, None, true).await.map(|_| ()),
+                Undo::Restore(id, previous) => self.pass_dao.update_password(id, previous).await,
+            };
+            if let Err(e) = result {
+                eprintln!("Could not undo the move to {}: {:#}", undo.id(), e);
+            }
+        }
+    }
+
+    /// Finds the version **spec** of the password **name** with the given **id**, where **spec** is a stage such as
+    /// `AWSPREVIOUS` or a unique prefix of a version id.
+    async fn find_version(&self, name: &str, id: &str, spec: &str) -> Result<PasswordVersion> {
+        let versions = self.pass_dao.list_password_versions(id).await?;
+        let (staged, others): (Vec<PasswordVersion>, Vec<PasswordVersion>) =
+            versions.into_iter().partition(|v| v.stages.iter().any(|s| s == spec));
+        let mut matches = match staged.is_empty() {
+            true => others.into_iter().filter(|v| v.id.starts_with(spec)).collect(),
+            false => staged,
+        };
+        match matches.len() {
+            0 => Err(AwsPassError::NotFound(format!("Version {} of {}", spec, name)).into()),
+            1 => Ok(matches.remove(0)),
+            n => Err(AwsPassError::Ambiguous(format!("Version {} of {}", spec, name), n).into()),
+        }
+    }
+
+    /// Gets the version **spec** of the password **name**, see **find_version**.
+    async fn get_password_version(&self, name: &str, spec: &str) -> Result<(PasswordVersion, Password)> {
+        let details = self.find_password(name).await?;
+        let version = self.find_version(name, &details.id, spec).await?;
+        let password = self.pass_dao.get_password_version(&details.id, &version.id).await?;
+        Ok((version, password))
+    }
+
+    async fn get_password_by_name(&self, name: &str) -> Result<Password> {
+        let details = self.find_password(name).await?;
+        self.pass_dao.get_password(&details.id).await
     }
 }
 
 #[async_trait]
 impl PassStore for DefaultPassStore {
-    async fn init(&self) {
-        self.ensure_empty_store_dir();
+    async fn init(&self) -> Result<()> {
+        self.ensure_empty_store_dir()?;
         println!(
             "Please provide AWS credentials for a user with an associated policy with MFA-protected SecretsManager \
             permissions"
         );
-        let creds = self.get_store_details();
-        self.write_store_details(&creds);
+        let creds = self.get_store_details()?;
+        self.write_store_details(&creds)
     }
 
-    async fn list(&self, prefix: Option<&str>) {
-        let ssfilters = STORE_FILTERS
-            .iter()
-            .map(|f| (f.0.to_string(), f.1.iter().map(|s| s.to_string()).collect()))
-            .collect();
-        let filters: Vec<Filter> = [
-            ssfilters,
-            prefix
-                .map(|p| vec![("name".to_string(), vec![p.to_string()])])
-                .unwrap_or_default(),
-        ]
-        .concat();
-        let passwords = self.pass_dao.list_passwords(&filters).await.unwrap();
-        let names: Vec<String> = passwords.into_iter().map(|p| p.name).collect();
-        println!("{}", names.join("\n"));
+    async fn list(&self, prefix: Option<&str>, flat: bool, depth: Option<usize>) -> Result<()> {
+        self.ensure_initialized()?;
+        if !output::is_text() {
+            let passwords = self.list_passwords(prefix).await?;
+            return output::print(&passwords.iter().map(to_json).collect());
+        }
+        if flat {
+            let passwords = self.list_passwords(prefix).await?;
+            let names: Vec<String> = passwords.into_iter().map(|p| p.name).collect();
+            println!("{}", names.join("\n"));
+            return Ok(());
+        }
+        // A prefix naming a folder shows the folder's subtree, any other prefix the passwords starting with it
+        let folder = prefix.map(|p| format!("{}/", p.trim_end_matches('/')));
+        let mut passwords = match &folder {
+            Some(folder) => self.list_passwords(Some(folder)).await?,
+            None => Vec::new(),
+        };
+        let (root, strip) = match &folder {
+            Some(folder) if !passwords.is_empty() => (folder.trim_end_matches('/').to_string(), folder.as_str()),
+            _ => {
+                passwords = self.list_passwords(prefix).await?;
+                ("Password Store".to_string(), "")
+            }
+        };
+        // The name filter also matches words within names, names outside the folder are left out
+        let tree = Tree::new(passwords.iter().filter_map(|p| p.name.strip_prefix(strip)));
+        print!("{}", tree.render(&root, depth, util::use_color()));
+        Ok(())
     }
 
-    async fn show(&self, name: &str) {
-        let password = self.get_password_by_name(name).await.unwrap();
-        println!("{}", password.value);
+    async fn find(&self, term: &str) -> Result<()> {
+        self.ensure_initialized()?;
+        let mut found = fuzzy::rank(term, self.list_passwords(None).await?, |p| p.name.as_str());
+        let store_tag = &self.settings.store.tag;
+        let mut others = self.search_passwords("description", term).await?;
+        // The store's own tag matches every password, only other tags count
+        others.extend(
+            self.search_passwords("tag-value", term)
+                .await?
+                .into_iter()
+                .filter(|p| p.tags.iter().any(|t| t != store_tag && t.1.starts_with(term))),
+        );
+        others.sort_by(|a, b| a.name.cmp(&b.name));
+        for password in others {
+            if !found.iter().any(|p| p.id == password.id) {
+                found.push(password);
+            }
+        }
+        if !output::is_text() {
+            return output::print(&found.iter().map(to_json).collect());
+        }
+        for password in found {
+            println!("{}", password.name);
+        }
+        Ok(())
     }
 
-    async fn insert(&self, name: &str) {
-        let value = util::prompt_stdin_line("Enter password:");
-        let tags: Vec<Tag> = vec![(STORE_TAGS.0.to_string(), STORE_TAGS.1.to_string())];
-        self.pass_dao.create_password(name, &value, Some(&tags)).await.unwrap();
+    async fn grep(&self, pattern: &Regex, prefix: Option<&str>, yes: bool) -> Result<()> {
+        self.ensure_initialized()?;
+        let passwords = self.list_passwords(prefix).await?;
+        if passwords.is_empty() {
+            return match output::is_text() {
+                true => Ok(()),
+                false => output::print(&json!([])),
+            };
+        }
+        let question = format!("Decrypt the values of {} passwords to search them?", passwords.len());
+        if !yes && !prompt::confirm("confirm-grep", &question)? {
+            if !output::is_text() {
+                return output::print(&json!([]));
+            }
+            println!("Nothing searched");
+            return Ok(());
+        }
+        let total = passwords.len();
+        let mut results: Vec<(String, Result<Password>)> = stream::iter(passwords)
+            .map(|password| async move {
+                let result = self.pass_dao.get_password(&password.id).await;
+                (password.name, result)
+            })
+            .buffer_unordered(MAX_CONCURRENT_REQUESTS)
+            .collect()
+            .await;
+        results.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let color = util::use_color();
+        let mut matches = Vec::new();
+        let mut failed = 0;
+        for (name, result) in results {
+            match result {
+                Ok(password) => {
+                    let lines: Vec<(usize, &str)> = password
+                        .value
+                        .lines()
+                        .enumerate()
+                        .filter(|(_, line)| pattern.is_match(line))
+                        .map(|(i, line)| (i + 1, line))
+                        .collect();
+                    if lines.is_empty() {
+                        continue;
+                    }
+                    if output::is_text() {
+                        println!(
+                            "{}",
+                            if color {
+                                format!("{}{}{}", BLUE_BOLD, name, RESET)
+                            } else {
+                                name.clone()
+                            }
+                        );
+                        for (number, line) in &lines {
+                            println!("{}: {}", number, highlight(pattern, line, color));
+                        }
+                    } else {
+                        let lines: Vec<Value> = lines
+                            .iter()
+                            .map(|(number, line)| json!({"line": number, "text": line}))
+                            .collect();
+                        matches.push(json!({"name": name, "lines": lines}));
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Could not search {}: {:#}", name, e);
+                    failed += 1;
+                }
+            }
+        }
+        if !output::is_text() {
+            output::print(&Value::Array(matches))?;
+        }
+        if failed > 0 {
+            return Err(anyhow::anyhow!("{} of {} passwords were not searched", failed, total));
+        }
+        Ok(())
     }
 
-    async fn edit(&self, name: &str) {
-        let password = self.get_password_by_name(name).await.unwrap();
-        let updated_password = edit::edit(password.value).unwrap().trim_end().to_string();
-        self.pass_dao
-            .update_password(&password.id, &updated_password)
-            .await
-            .unwrap();
+    async fn names(&self) -> Result<Vec<String>> {
+        self.ensure_initialized()?;
+        Ok(self.cached_listing(false).await?.into_iter().map(|p| p.name).collect())
     }
 
-    async fn generate(&self, name: &str, exclude_chars: Option<&str>, length: Option<&i64>) {
-        let tags: Vec<Tag> = vec![(STORE_TAGS.0.to_string(), STORE_TAGS.1.to_string())];
-        let password = self
-            .pass_dao
-            .create_random_password(name, exclude_chars, length, Some(&tags))
-            .await
-            .unwrap();
-        println!("{}", password.value);
+    fn session_expiration(&self) -> Option<DateTime<Utc>> {
+        self.creds_provider.expiration()
+    }
+
+    async fn pick(&self, refresh: bool, clip_timeout: u64) -> Result<()> {
+        self.ensure_initialized()?;
+        if !util::stdin_is_tty() || !util::stderr_is_tty() {
+            return Err(AwsPassError::InvalidInput("pick needs a terminal".to_string()).into());
+        }
+        let passwords = self.cached_listing(refresh).await?;
+        let names: Vec<String> = passwords.iter().map(|p| p.name.clone()).collect();
+        let (index, action) = match picker::pick(&names, |i| preview(&passwords[i]))? {
+            Some(picked) => picked,
+            None => return Ok(()),
+        };
+        let name = &passwords[index].name;
+        match action {
+            Action::Show => self.show(name, None, None, None).await,
+            // Copying without a selector copies the password alone, like `show --clip`
+            Action::Clip => self.show(name, None, Some(clip_timeout), None).await,
+            Action::Edit => self.edit(name).await,
+            Action::Otp => self.otp(name, None, false).await,
+            Action::Username => {
+                let username = Selector::Field("username".to_string());
+                self.show(name, Some(&username), Some(clip_timeout), None).await
+            }
+        }
+    }
+
+    async fn show(
+        &self,
+        name: &str,
+        selector: Option<&Selector>,
+        clip_timeout: Option<u64>,
+        version: Option<&str>,
+    ) -> Result<()> {
+        let password = match version {
+            Some(spec) => self.get_password_version(name, spec).await?.1,
+            None => self.get_password_by_name(name).await?,
+        };
+        let entry = Entry::parse(&password.value);
+        let value = selected_value(name, password.value, &entry, selector, clip_timeout.is_some())?;
+        match clip_timeout {
+            Some(timeout) => {
+                clipboard::copy_with_restore(&value, timeout)?;
+                if !output::is_text() {
+                    return output::print(&json!({"name": name, "copied": true, "timeout": timeout}));
+                }
+                println!("Copied {} to clipboard, will clear in {} seconds", name, timeout);
+            }
+            None if !output::is_text() => {
+                let mut json = match selector {
+                    Some(_) => json!({ "value": value }),
+                    None => entry.to_json(),
+                };
+                json["name"] = json!(name);
+                output::print(&json)?;
+            }
+            None => println!("{}", value),
+        }
+        Ok(())
+    }
+
+    async fn insert(&self, name: &str, mode: InsertMode) -> Result<()> {
+        self.ensure_initialized()?;
+        let value = match mode {
+            InsertMode::Hidden => prompt::ask_hidden_confirmed("password", "Password:")?,
+            InsertMode::Echo => prompt::ask("password", "Password:")?,
+            InsertMode::Stdin => util::read_stdin_to_end()?,
+            InsertMode::Multiline => {
+                eprintln!("Enter contents of {} and press Ctrl+D when finished:", name);
+                util::read_stdin_to_end()?
+                    .trim_end_matches(&['\r', '\n'][..])
+                    .to_string()
+            }
+            InsertMode::Fields(fields) => {
+                let mut values = Vec::new();
+                for (key, value) in fields {
+                    let value = match value {
+                        Some(v) => v,
+                        None => prompt::ask_hidden_confirmed(&format!("field-{}", key), &format!("{}:", key))?,
+                    };
+                    values.push((key, value));
+                }
+                Entry::from_fields(&values).to_value()
+            }
+        };
+        if value.is_empty() {
+            return Err(AwsPassError::InvalidInput("Password must be non-empty".to_string()).into());
+        }
+        self.create_password(name, &value).await?;
+        Ok(())
+    }
+
+    async fn edit(&self, name: &str) -> Result<()> {
+        let password = self.get_password_by_name(name).await?;
+        let updated_password = match Entry::parse(&password.value).to_yaml() {
+            Some(yaml) => self.edit_yaml(yaml)?.to_value(),
+            // Editors add a final newline, any other whitespace is part of the password
+            None => edit::edit(password.value)?
+                .trim_end_matches(&['\r', '\n'][..])
+                .to_string(),
+        };
+        self.pass_dao.update_password(&password.id, &updated_password).await
     }
 
-    async fn remove(&self, name: &str) {
-        let filters: Vec<Filter> = STORE_FILTERS
+    async fn generate(&self, name: &str, options: &GenerateOptions) -> Result<()> {
+        self.ensure_initialized()?;
+        let config = &self.settings.config;
+        let policy = self.generate_policy(options)?;
+        let value = match options.source.or(config.generate_source()?).unwrap_or(Source::Local) {
+            Source::Aws if policy.words.is_some() => {
+                return Err(AwsPassError::InvalidInput("Passphrases are only generated locally".to_string()).into())
+            }
+            Source::Aws => {
+                let excluded = policy.excluded();
+                self.pass_dao
+                    .random_password(
+                        Some(excluded.as_str()).filter(|e| !e.is_empty()),
+                        Some(&(policy.length as i64)),
+                        (!policy.lowercase, !policy.uppercase, !policy.digits, !policy.symbols),
+                        policy.require_each_type,
+                    )
+                    .await?
+            }
+            Source::Local => {
+                let wordlist = match options
+                    .wordlist
+                    .clone()
+                    .or(config.generate.wordlist.as_ref().map(PathBuf::from))
+                {
+                    Some(path) if policy.words.is_some() => Some(fs::read_to_string(&path).map_err(|e| {
+                        AwsPassError::InvalidInput(format!("Could not read {}: {}", path.display(), e))
+                    })?),
+                    _ => None,
+                };
+                policy.generate(wordlist.as_deref())?
+            }
+        };
+        match options.in_place {
+            true => {
+                let password = self.get_password_by_name(name).await?;
+                let entry = Entry::parse(&password.value);
+                if entry.is_plain() && !options.force {
+                    return Err(AwsPassError::InvalidInput(format!(
+                        "{} holds nothing but its password, --force overwrites it",
+                        name
+                    ))
+                    .into());
+                }
+                self.forget_listing();
+                let updated = entry.with_password(&value).to_value();
+                self.pass_dao.update_password(&password.id, &updated).await?;
+            }
+            false => {
+                self.create_password(name, &value).await?;
+            }
+        }
+        if !output::is_text() {
+            return output::print(&json!({"name": name, "password": value}));
+        }
+        println!("{}", value);
+        Ok(())
+    }
+
+    async fn remove(&self, name: &str, options: &RemoveOptions) -> Result<()> {
+        self.forget_listing();
+        let passwords = match options.recursive {
+            true => {
+                self.ensure_initialized()?;
+                // The name filter also matches words within names, only the password and the ones under it count
+                let folder = format!("{}/", name.trim_end_matches('/'));
+                let passwords: Vec<PasswordDetails> = self
+                    .list_passwords(Some(name))
+                    .await?
+                    .into_iter()
+                    .filter(|p| p.name == name || p.name.starts_with(&folder))
+                    .collect();
+                if passwords.is_empty() {
+                    return Err(AwsPassError::NotFound(name.to_string()).into());
+                }
+                passwords
+            }
+            false => vec![self.find_password(name).await?],
+        };
+        let how = if options.force { " for good" } else { "" };
+        if options.dry_run {
+            if !output::is_text() {
+                return output::print(&json!(passwords
+                    .iter()
+                    .map(|p| json!({"name": p.name, "arn": p.id}))
+                    .collect::<Vec<Value>>()));
+            }
+            for password in &passwords {
+                println!("Would remove {}{}", password.name, how);
+            }
+            return Ok(());
+        }
+        if (options.recursive || options.force) && !options.yes {
+            let question = match passwords.as_slice() {
+                [password] => format!("Remove {}{}?", password.name, how),
+                _ => {
+                    // Listed with the question, apart from the output
+                    for password in &passwords {
+                        eprintln!("{}", password.name);
+                    }
+                    format!("Remove these {} passwords{}?", passwords.len(), how)
+                }
+            };
+            if !prompt::confirm("confirm-remove", &question)? {
+                if !output::is_text() {
+                    return output::print(&json!([]));
+                }
+                println!("Nothing removed");
+                return Ok(());
+            }
+        }
+        let total = passwords.len();
+        let results: Vec<(PasswordDetails, Result<Option<String>>)> = stream::iter(passwords)
+            .map(|password| async move {
+                let result = self
+                    .pass_dao
+                    .delete_password(&password.id, options.recovery_days, options.force)
+                    .await
+                    .map(|d| d.map(|d| d.format("%Y-%m-%d %H:%M:%S UTC").to_string()));
+                (password, result)
+            })
+            .buffer_unordered(MAX_CONCURRENT_REQUESTS)
+            .collect()
+            .await;
+        let mut removed = Vec::new();
+        let mut failed = 0;
+        for (password, result) in results {
+            match result {
+                Ok(deletion_date) => {
+                    match &deletion_date {
+                        _ if !output::is_text() => {}
+                        Some(date) if !options.force => println!(
+                            "Removed {}, `aws-pass restore` restores it until {}",
+                            password.name, date
+                        ),
+                        _ => println!("Removed {}{}", password.name, how),
+                    }
+                    removed.push(TrashRecord {
+                        store: self.settings.store.name.clone(),
+                        region: self.settings.region.clone(),
+                        name: password.name,
+                        arn: password.id,
+                        deletion_date,
+                    });
+                }
+                Err(e) => {
+                    eprintln!("Could not remove {}: {:#}", password.name, e);
+                    failed += 1;
+                }
+            }
+        }
+        if !output::is_text() {
+            output::print(&json!(removed
+                .iter()
+                .map(|r| json!({"name": r.name, "arn": r.arn, "deletion_date": r.deletion_date}))
+                .collect::<Vec<Value>>()))?;
+        }
+        if !options.force {
+            self.trash().add(removed)?;
+        }
+        if failed > 0 {
+            return Err(anyhow::anyhow!("{} of {} passwords were not removed", failed, total));
+        }
+        Ok(())
+    }
+
+    async fn trash(&self) -> Result<()> {
+        self.ensure_initialized()?;
+        let trash = self.trash();
+        let (own, mut kept): (Vec<TrashRecord>, Vec<TrashRecord>) =
+            trash.load()?.into_iter().partition(|r| self.is_own_record(r));
+        let mut pending = Vec::new();
+        for record in own {
+            // Records of passwords restored or deleted for good since are pruned
+            match self.pass_dao.describe_password(&record.arn).await {
+                Ok(details) if details.deleted.is_some() => pending.push(record),
+                Ok(_) => {}
+                Err(e) if matches!(e.downcast_ref(), Some(AwsPassError::NotFound(_))) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if output::is_text() {
+            for record in &pending {
+                println!(
+                    "{}  deleted for good at {}",
+                    record.name,
+                    record.deletion_date.as_deref().unwrap_or("-")
+                );
+            }
+        } else {
+            output::print(&json!(pending
+                .iter()
+                .map(|r| json!({"name": r.name, "arn": r.arn, "deletion_date": r.deletion_date}))
+                .collect::<Vec<Value>>()))?;
+        }
+        kept.extend(pending);
+        trash.save(&kept)
+    }
+
+    async fn restore(&self, name: &str) -> Result<()> {
+        self.ensure_initialized()?;
+        self.forget_listing();
+        let trash = self.trash();
+        let mut records = trash.load()?;
+        // Passwords removed elsewhere aren't recorded, their secret's name restores them just as well
+        let id = records
             .iter()
-            .map(|f| (f.0.to_string(), f.1.iter().map(|s| s.to_string()).collect()))
-            .collect();
-        self.pass_dao
-            .delete_password_by_name(name, Some(&filters))
-            .await
-            .unwrap();
+            .rev()
+            .find(|r| self.is_own_record(r) && r.name == name)
+            .map_or_else(|| self.secret_name(name), |r| r.arn.clone());
+        self.pass_dao.restore_password(&id).await?;
+        records.retain(|r| !(self.is_own_record(r) && r.name == name));
+        trash.save(&records)?;
+        if !output::is_text() {
+            return output::print(&json!({"name": name, "arn": id}));
+        }
+        println!("Restored {}", name);
+        Ok(())
+    }
+
+    async fn mv(&self, from: &str, to: &str, force: bool) -> Result<()> {
+        self.ensure_initialized()?;
+        self.forget_listing();
+        let plan = self.transfer_plan(from, to, true).await?;
+        // Every destination is written before any source is deleted, so that a failure can be undone
+        let mut moved = Vec::new();
+        for (src, dst) in &plan {
+            match self.move_to(src, dst, force).await {
+                Ok(m) => moved.push(m),
+                Err(e) => {
+                    self.undo_moves(moved).await;
+                    return Err(e.context(format!("Could not move {} to {}, nothing was moved", src, dst)));
+                }
+            }
+        }
+        let mut done = Vec::new();
+        let mut failed = 0;
+        // Sources are deleted without a recovery window, which would keep their names reserved, the destinations
+        // already read back as written
+        for ((src, dst), m) in plan.iter().zip(moved) {
+            match self.pass_dao.delete_password(&m.source_id, None, true).await {
+                Ok(_) if !output::is_text() => done.push(json!({"from": src, "to": dst})),
+                Ok(_) => println!("Moved {} to {}", src, dst),
+                Err(e) => {
+                    eprintln!("Could not move {} to {}: {:#}", src, dst, e);
+                    self.undo_moves(vec![m]).await;
+                    failed += 1;
+                }
+            }
+        }
+        if !output::is_text() {
+            output::print(&json!(done))?;
+        }
+        if failed > 0 {
+            return Err(anyhow::anyhow!("{} of {} passwords were not moved", failed, plan.len()));
+        }
+        Ok(())
+    }
+
+    async fn cp(&self, from: &str, to: &str, force: bool, dest_settings: Option<&Settings>) -> Result<()> {
+        self.ensure_initialized()?;
+        let dest = dest_settings.map(|settings| {
+            DefaultPassStore::with_creds(self.store_dir.clone(), settings, self.creds_provider.clone())
+        });
+        let dest = dest.as_ref().unwrap_or(self);
+        let same_store =
+            dest.settings.store.name == self.settings.store.name && dest.settings.sm_region == self.settings.sm_region;
+        let plan = self.transfer_plan(from, to, same_store).await?;
+        let mut done = Vec::new();
+        let mut failed = 0;
+        for (src, dst) in &plan {
+            let result = async {
+                let source = self.find_password(src).await?;
+                let value = self.pass_dao.get_password(&source.id).await?.value;
+                self.write_to(dest, &source, &value, dst, force).await
+            };
+            match result.await {
+                Ok(_) if !output::is_text() => done.push(json!({"from": src, "to": dst})),
+                Ok(_) => println!("Copied {} to {}", src, dst),
+                Err(e) => {
+                    eprintln!("Could not copy {} to {}: {:#}", src, dst, e);
+                    failed += 1;
+                }
+            }
+        }
+        if !output::is_text() {
+            output::print(&json!(done))?;
+        }
+        if failed > 0 {
+            return Err(anyhow::anyhow!(
+                "{} of {} passwords were not copied",
+                failed,
+                plan.len()
+            ));
+        }
+        Ok(())
+    }
+
+    async fn history(&self, name: &str) -> Result<()> {
+        let details = self.find_password(name).await?;
+        let versions = self.pass_dao.list_password_versions(&details.id).await?;
+        if !output::is_text() {
+            return output::print(&json!(versions
+                .iter()
+                .map(|v| json!({"id": v.id, "created": output::date(v.created), "stages": v.stages}))
+                .collect::<Vec<Value>>()));
+        }
+        for version in versions {
+            let created = version
+                .created
+                .map_or("-".to_string(), |c| c.format("%Y-%m-%d %H:%M:%S UTC").to_string());
+            println!("{}  {}  {}", version.id, created, version.stages.join(","));
+        }
+        Ok(())
+    }
+
+    async fn diff(&self, name: &str, from: &str, to: &str, unmask: bool) -> Result<()> {
+        let (from_version, from_password) = self.get_password_version(name, from).await?;
+        let (to_version, to_password) = self.get_password_version(name, to).await?;
+        let old = Entry::parse(&from_password.value).diff_lines();
+        let new = Entry::parse(&to_password.value).diff_lines();
+        let raw = |lines: &[(String, String)]| lines.iter().map(|(r, _)| r.clone()).collect::<Vec<String>>();
+        let shown = |(r, m): &(String, String)| if unmask { r.clone() } else { m.clone() };
+        let changes = diff::diff(&raw(&old), &raw(&new));
+        if !output::is_text() {
+            let lines = changes.iter().map(|change| match *change {
+                Change::Same(j) => json!({"change": "same", "line": shown(&new[j])}),
+                Change::Removed(i) => json!({"change": "removed", "line": shown(&old[i])}),
+                Change::Added(j) => json!({"change": "added", "line": shown(&new[j])}),
+            });
+            return output::print(&json!({
+                "name": name,
+                "from": from_version.id,
+                "to": to_version.id,
+                "lines": lines.collect::<Vec<Value>>(),
+            }));
+        }
+        println!("--- {} {}", name, from_version.id);
+        println!("+++ {} {}", name, to_version.id);
+        for change in changes {
+            match change {
+                Change::Same(j) => println!("  {}", shown(&new[j])),
+                Change::Removed(i) => println!("- {}", shown(&old[i])),
+                Change::Added(j) => println!("+ {}", shown(&new[j])),
+            }
+        }
+        Ok(())
+    }
+
+    async fn rollback(&self, name: &str, version: &str) -> Result<()> {
+        let details = self.find_password(name).await?;
+        let version = self.find_version(name, &details.id, version).await?;
+        if version.stages.iter().any(|s| s == CURRENT_STAGE) {
+            return Err(AwsPassError::InvalidInput(format!(
+                "Version {} is already the current version of {}",
+                version.id, name
+            ))
+            .into());
+        }
+        self.pass_dao.set_current_version(&details.id, &version.id).await?;
+        if !output::is_text() {
+            return output::print(&json!({"name": name, "version": version.id}));
+        }
+        println!("Rolled {} back to version {}", name, version.id);
+        Ok(())
+    }
+
+    async fn otp(&self, name: &str, clip_timeout: Option<u64>, show_remaining: bool) -> Result<()> {
+        let password = self.get_password_by_name(name).await?;
+        let entry = Entry::parse(&password.value);
+        let uri = OtpUri::parse(
+            &entry
+                .otp_uri()
+                .ok_or_else(|| AwsPassError::NotFound(format!("OTP URI of {}", name)))?,
+        )?;
+        let now = otp::now();
+        let code = uri.code(now);
+        if uri.kind == OtpKind::Hotp {
+            // Advance the counter before handing out the code so that it's never handed out twice
+            let updated = entry.with_otp_uri(uri.next().as_str());
+            self.pass_dao.update_password(&password.id, &updated.to_value()).await?;
+        }
+        match clip_timeout {
+            Some(timeout) => {
+                clipboard::copy_with_restore(&code, timeout)?;
+                if !output::is_text() {
+                    return output::print(&json!({
+                        "name": name,
+                        "copied": true,
+                        "timeout": timeout,
+                        "remaining": uri.remaining(now),
+                    }));
+                }
+                println!(
+                    "Copied OTP code for {} to clipboard, will clear in {} seconds",
+                    name, timeout
+                );
+            }
+            None if !output::is_text() => {
+                return output::print(&json!({"name": name, "code": code, "remaining": uri.remaining(now)}));
+            }
+            None => println!("{}", code),
+        }
+        if let Some(remaining) = uri.remaining(now).filter(|_| show_remaining) {
+            println!("{} seconds remaining", remaining);
+        }
+        Ok(())
+    }
+
+    async fn insert_otp(&self, name: &str, force: bool) -> Result<()> {
+        self.ensure_initialized()?;
+        let uri = if util::stdin_is_tty() {
+            prompt::ask_hidden("otp-uri", "otpauth:// URI:")?
+        } else {
+            util::read_stdin_to_end()?
+        };
+        let uri = OtpUri::parse(&uri)?;
+        match self.get_password_by_name(name).await {
+            Ok(password) => {
+                let entry = Entry::parse(&password.value);
+                if entry.otp_uri().is_some() && !force {
+                    return Err(AwsPassError::AlreadyExists(format!("OTP URI of {}", name)).into());
+                }
+                let updated = entry.with_otp_uri(uri.as_str());
+                self.pass_dao.update_password(&password.id, &updated.to_value()).await
+            }
+            Err(e) if matches!(e.downcast_ref(), Some(AwsPassError::NotFound(_))) => {
+                self.create_password(name, uri.as_str()).await?;
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn otp_uri(&self, name: &str) -> Result<()> {
+        let password = self.get_password_by_name(name).await?;
+        let uri = Entry::parse(&password.value)
+            .otp_uri()
+            .ok_or_else(|| AwsPassError::NotFound(format!("OTP URI of {}", name)))?;
+        if !output::is_text() {
+            return output::print(&json!({"name": name, "uri": uri}));
+        }
+        println!("{}", uri);
+        Ok(())
+    }
+
+    async fn set_mfa_seed(&self) -> Result<()> {
+        let mfa_seed = self.mfa_seed().await?;
+        let input = if util::stdin_is_tty() {
+            prompt::ask_hidden("mfa-seed", "MFA device seed (base32 or otpauth:// URI):")?
+        } else {
+            util::read_stdin_to_end()?
+        };
+        let seed = if input.trim_start().starts_with("otpauth://") {
+            let uri = OtpUri::parse(&input)?;
+            if uri.kind != OtpKind::Totp || uri.algorithm != Algorithm::Sha1 || uri.digits != 6 || uri.period != 30 {
+                return Err(AwsPassError::InvalidInput(
+                    "AWS virtual MFA devices use 6 digit SHA1 TOTP codes with a 30 second period".to_string(),
+                )
+                .into());
+            }
+            uri.secret
+        } else {
+            otp::decode_base32(&input)
+                .ok_or_else(|| AwsPassError::InvalidInput("MFA device seed must be base32".to_string()))?
+        };
+        // The seed is only as safe as the key it's encrypted with, stores initialized before the credentials were
+        // written privately get them restricted to their owner
+        for filename in [CREDENTIALS_FILENAME, TOKEN_SERIAL_FILENAME] {
+            fs::set_permissions(self.store_dir.join(filename), Permissions::from_mode(0o600))?;
+        }
+        mfa_seed.store(&seed)?;
+        if !output::is_text() {
+            return output::print(&json!({"mfa_seed": true}));
+        }
+        println!("MFA token codes will be computed from the stored seed");
+        Ok(())
+    }
+
+    async fn remove_mfa_seed(&self) -> Result<()> {
+        self.mfa_seed().await?.remove()?;
+        if !output::is_text() {
+            return output::print(&json!({"mfa_seed": false}));
+        }
+        println!("MFA token codes will be asked for");
+        Ok(())
+    }
+}
+
+/// The listing of a password for structured output.
+fn to_json(password: &PasswordDetails) -> Value {
+    let tags: Map<String, Value> = password
+        .tags
+        .iter()
+        .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+        .collect();
+    json!({
+        "name": password.name,
+        "arn": password.id,
+        "description": password.description,
+        "tags": tags,
+        "created": output::date(password.created),
+        "last_changed": output::date(password.last_changed),
+        "last_accessed": output::date(password.last_accessed),
+    })
+}
+
+/// Colors the matches of **pattern** in **line** when **color** is set.
+fn highlight(pattern: &Regex, line: &str, color: bool) -> String {
+    if !color {
+        return line.to_string();
+    }
+    pattern
+        .replace_all(line, |c: &Captures| format!("{}{}{}", RED_BOLD, &c[0], RESET))
+        .into_owned()
+}
+
+/// The metadata of a password `pick` shows for it, never its value.
+fn preview(password: &CachedPassword) -> Vec<String> {
+    let mut lines = vec![password.name.clone()];
+    if let Some(description) = &password.description {
+        lines.push(description.clone());
+    }
+    lines.push(String::new());
+    for (label, date) in [
+        ("Created", &password.created),
+        ("Changed", &password.last_changed),
+        ("Accessed", &password.last_accessed),
+    ] {
+        lines.push(format!("{:<9} {}", label, date.as_deref().unwrap_or("-")));
+    }
+    for (key, value) in &password.tags {
+        lines.push(format!("{:<9} {} = {}", "Tag", key, value));
+    }
+    lines
+}
+
+/// The part of the password **name** holding **value**, parsed as **entry**, that `show` prints or copies. Copying
+/// without a **selector** copies only the password, like `pass -c`, never the entry's metadata or OTP seed.
+fn selected_value(name: &str, value: String, entry: &Entry, selector: Option<&Selector>, clip: bool) -> Result<String> {
+    let selected = match selector {
+        Some(Selector::Line(n)) => entry
+            .line(*n)
+            .ok_or_else(|| AwsPassError::NotFound(format!("Line {} of {}", n, name)))?,
+        Some(Selector::Field(key)) => entry
+            .field(key)
+            .ok_or_else(|| AwsPassError::NotFound(format!("Field {} of {}", key, name)))?,
+        None if clip => entry.password(),
+        None => value,
+    };
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dao::pass_dao::ExcludedClasses, settings::SettingsArgs};
+    use rusoto_core::credential::StaticProvider;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    /// The names and values of the stub's passwords.
+    type Passwords = Arc<Mutex<Vec<(String, String)>>>;
+
+    /// Stands in for Secrets Manager, listing every password whatever the filters as its name filter also matches
+    /// words within names. Passwords are identified by their names.
+    struct StubPassDao {
+        passwords: Passwords,
+    }
+
+    #[async_trait]
+    impl PassDao for StubPassDao {
+        async fn create_password(
+            &self,
+            name: &str,
+            value: &str,
+            _: Option<&str>,
+            _: Option<&[Tag]>,
+            _: Option<&str>,
+        ) -> Result<Password> {
+            self.passwords
+                .lock()
+                .unwrap()
+                .push((name.to_string(), value.to_string()));
+            Ok(Password {
+                id: name.to_string(),
+                value: value.to_string(),
+            })
+        }
+
+        async fn random_password(
+            &self,
+            _: Option<&str>,
+            _: Option<&i64>,
+            _: ExcludedClasses,
+            _: bool,
+        ) -> Result<String> {
+            unimplemented!()
+        }
+
+        async fn get_password(&self, id: &str) -> Result<Password> {
+            let passwords = self.passwords.lock().unwrap();
+            let (_, value) = passwords
+                .iter()
+                .find(|(name, _)| name == id)
+                .ok_or_else(|| AwsPassError::NotFound(id.to_string()))?;
+            Ok(Password {
+                id: id.to_string(),
+                value: value.clone(),
+            })
+        }
+
+        async fn get_password_version(&self, _: &str, _: &str) -> Result<Password> {
+            unimplemented!()
+        }
+
+        async fn list_password_versions(&self, _: &str) -> Result<Vec<PasswordVersion>> {
+            unimplemented!()
+        }
+
+        async fn set_current_version(&self, _: &str, _: &str) -> Result<()> {
+            unimplemented!()
+        }
+
+        async fn update_password(&self, id: &str, value: &str) -> Result<()> {
+            let mut passwords = self.passwords.lock().unwrap();
+            if let Some(password) = passwords.iter_mut().find(|(name, _)| name == id) {
+                password.1 = value.to_string();
+            }
+            Ok(())
+        }
+
+        async fn delete_password(&self, id: &str, _: Option<i64>, _: bool) -> Result<Option<DateTime<Utc>>> {
+            self.passwords.lock().unwrap().retain(|(name, _)| name != id);
+            Ok(None)
+        }
+
+        async fn restore_password(&self, _: &str) -> Result<()> {
+            unimplemented!()
+        }
+
+        async fn describe_password(&self, _: &str) -> Result<PasswordDetails> {
+            unimplemented!()
+        }
+
+        async fn list_passwords(&self, _: &[Filter]) -> Result<Vec<PasswordDetails>> {
+            let tag = ("aws-pass".to_string(), "true".to_string());
+            Ok(self
+                .passwords
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(name, _)| PasswordDetails {
+                    id: name.clone(),
+                    name: name.clone(),
+                    tags: vec![tag.clone()],
+                    description: None,
+                    kms_key_id: None,
+                    deleted: None,
+                    created: None,
+                    last_changed: None,
+                    last_accessed: None,
+                })
+                .collect())
+        }
+    }
+
+    /// Opens an initialized store in **dir** holding the passwords of the given **names**, returning the store and
+    /// the names and values of the passwords it holds as they change.
+    fn stub_store(dir: &TempDir, names: &[&str]) -> (DefaultPassStore, Passwords) {
+        stub_store_with_config(dir, names, "version = 1\n")
+    }
+
+    /// Opens the store of **stub_store** with the given **config**.
+    fn stub_store_with_config(dir: &TempDir, names: &[&str], config: &str) -> (DefaultPassStore, Passwords) {
+        fs::write(dir.path().join(CREDENTIALS_FILENAME), "").unwrap();
+        fs::write(dir.path().join(TOKEN_SERIAL_FILENAME), "serial").unwrap();
+        fs::write(dir.path().join(crate::settings::config::CONFIG_FILENAME), config).unwrap();
+        let settings = Settings::resolve(dir.path(), SettingsArgs::default()).unwrap();
+        let passwords = Arc::new(Mutex::new(
+            names
+                .iter()
+                .map(|n| (n.to_string(), format!("value of {}", n)))
+                .collect(),
+        ));
+        let store = DefaultPassStore {
+            store_dir: dir.path().to_path_buf(),
+            settings,
+            creds_provider: SharedCredsProvider::new(StaticProvider::new_minimal("id".to_string(), "key".to_string())),
+            pass_dao: Box::new(StubPassDao {
+                passwords: passwords.clone(),
+            }),
+        };
+        (store, passwords)
+    }
+
+    fn names(passwords: &Mutex<Vec<(String, String)>>) -> Vec<String> {
+        passwords.lock().unwrap().iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    #[tokio::test]
+    async fn removes_only_the_folder_recursively() {
+        let dir = TempDir::new().unwrap();
+        let (store, passwords) = stub_store(&dir, &["team", "team/db", "team-ops/db", "my-team/db"]);
+        let options = RemoveOptions {
+            recursive: true,
+            yes: true,
+            force: true,
+            ..Default::default()
+        };
+        store.remove("team", &options).await.unwrap();
+        assert_eq!(names(&passwords), vec!["team-ops/db", "my-team/db"]);
+    }
+
+    #[tokio::test]
+    async fn moves_only_the_folder() {
+        let dir = TempDir::new().unwrap();
+        let (store, passwords) = stub_store(&dir, &["team/db", "my-team/web", "te", "ops/x"]);
+        store.mv("team/", "ops/", false).await.unwrap();
+        assert_eq!(names(&passwords), vec!["my-team/web", "te", "ops/x", "ops/db"]);
+        assert_eq!(
+            store.pass_dao.get_password("ops/db").await.unwrap().value,
+            "value of team/db"
+        );
+    }
+
+    #[test]
+    fn copies_only_the_password() {
+        for value in [
+            "secret\nusername: alice\notpauth://totp/x?secret=MZXW6YTB",
+            r#"{"username":"alice","password":"secret","otp":"otpauth://totp/x?secret=MZXW6YTB"}"#,
+        ] {
+            let entry = Entry::parse(value);
+            let selected = |clip| selected_value("web", value.to_string(), &entry, None, clip).unwrap();
+            assert_eq!(selected(true), "secret");
+            assert_eq!(selected(false), value);
+        }
+    }
+
+    #[test]
+    fn merges_config_policy_and_options() {
+        let dir = TempDir::new().unwrap();
+        let config = r#"
+version = 1
+
+[generate]
+length = 40
+exclude_chars = "abc"
+
+[policies.web]
+length = 16
+symbols = false
+"#;
+        let (store, _) = stub_store_with_config(&dir, &[], config);
+        let policy = |options: GenerateOptions| store.generate_policy(&options).unwrap();
+        assert_eq!(policy(GenerateOptions::default()).length, 40);
+        assert_eq!(policy(GenerateOptions::default()).exclude_chars, "abc");
+        let web = GenerateOptions {
+            policy: Some("web".to_string()),
+            ..Default::default()
+        };
+        assert_eq!((policy(web.clone()).length, policy(web.clone()).symbols), (16, false));
+        assert_eq!(policy(web.clone()).exclude_chars, "abc");
+        let overridden = policy(GenerateOptions {
+            length: Some(8),
+            no_digits: true,
+            exclude_chars: Some("x".to_string()),
+            ..web
+        });
+        assert_eq!(
+            (overridden.length, overridden.digits, overridden.symbols),
+            (8, false, false)
+        );
+        assert_eq!(overridden.exclude_chars, "x");
+        let too_long = GenerateOptions {
+            length: Some(4097),
+            ..Default::default()
+        };
+        assert!(store.generate_policy(&too_long).is_err());
+        let too_many = GenerateOptions {
+            words: Some(65),
+            ..Default::default()
+        };
+        assert!(store.generate_policy(&too_many).is_err());
     }
 }
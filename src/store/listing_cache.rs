@@ -0,0 +1,101 @@
+use crate::{
+    crypto::{self, EncryptionKey},
+    dao::pass_dao::{PasswordDetails, Tag},
+    util::write_private_file,
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+/// A password of the cached listing, its metadata only.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedPassword {
+    pub name: String,
+    pub arn: String,
+    pub description: Option<String>,
+    pub tags: Vec<Tag>,
+    pub created: Option<String>,
+    pub last_changed: Option<String>,
+    pub last_accessed: Option<String>,
+}
+
+impl From<PasswordDetails> for CachedPassword {
+    fn from(details: PasswordDetails) -> CachedPassword {
+        let format = |date: Option<DateTime<Utc>>| date.map(|d| d.format("%Y-%m-%d %H:%M:%S UTC").to_string());
+        CachedPassword {
+            name: details.name,
+            arn: details.id,
+            description: details.description,
+            tags: details.tags,
+            created: format(details.created),
+            last_changed: format(details.last_changed),
+            last_accessed: format(details.last_accessed),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Listing {
+    expires_at: Option<i64>,
+    passwords: Vec<CachedPassword>,
+}
+
+/// An encrypted file holding a store's listing for `pick`. The key is derived from the session's secret access key,
+/// so the listing can only be read during the session it was written in, and the store's name and region are bound
+/// to the ciphertext.
+pub struct ListingCache {
+    path: PathBuf,
+    key: EncryptionKey,
+    store: String,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl ListingCache {
+    pub fn new(
+        path: PathBuf,
+        session_secret_access_key: &str,
+        store: &str,
+        region: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> ListingCache {
+        ListingCache {
+            path,
+            key: crypto::derive_key(session_secret_access_key, "aws-pass listing cache"),
+            store: format!("{}\n{}", store, region),
+            expires_at,
+        }
+    }
+
+    /// Loads the cached listing if present, readable and the session it was written in hasn't expired.
+    pub fn load(&self) -> Option<Vec<CachedPassword>> {
+        if !self.path.exists() {
+            return None;
+        }
+        match self.read() {
+            Ok(listing) if listing.expires_at.is_none_or(|e| e > Utc::now().timestamp()) => Some(listing.passwords),
+            Ok(_) => None,
+            Err(e) => {
+                info!("Ignoring unreadable listing cache: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Stores the given **passwords** in the cache.
+    pub fn store(&self, passwords: &[CachedPassword]) -> Result<()> {
+        let listing = Listing {
+            expires_at: self.expires_at.map(|e| e.timestamp()),
+            passwords: passwords.to_vec(),
+        };
+        let data = crypto::encrypt(&self.key, &serde_json::to_vec(&listing)?, self.store.as_bytes())?;
+        Ok(write_private_file(&self.path, &data)?)
+    }
+
+    fn read(&self) -> Result<Listing> {
+        let data = fs::read(&self.path)?;
+        let json = crypto::decrypt(&self.key, &data, self.store.as_bytes())?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+}
@@ -1,2 +1,4 @@
 pub mod default_pass_store;
+pub mod listing_cache;
 pub mod pass_store;
+pub mod trash;
@@ -1,12 +1,126 @@
+use crate::{generator::Source, settings::Settings};
+use anyhow::Result;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::path::PathBuf;
+
+/// How `insert` reads the password's value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InsertMode {
+    /// Prompts twice without echoing, the entries must match.
+    Hidden,
+    /// Prompts once, echoing the value.
+    Echo,
+    /// Reads all of stdin without prompting.
+    Stdin,
+    /// Reads lines, echoing them, until end of input.
+    Multiline,
+    /// Builds a JSON object from the given fields, prompting for the values that are missing.
+    Fields(Vec<(String, Option<String>)>),
+}
+
+/// Selects part of an entry for `show`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+    /// The given line, starting at 1.
+    Line(usize),
+    /// The given metadata field.
+    Field(String),
+}
+
+/// How `remove` removes passwords.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RemoveOptions {
+    /// Days during which removed passwords can be restored, 30 when not given.
+    pub recovery_days: Option<i64>,
+    /// Deletes passwords for good instead.
+    pub force: bool,
+    /// Removes the password of the name given and every password under it as a folder.
+    pub recursive: bool,
+    /// Skips confirmation.
+    pub yes: bool,
+    /// Only shows what would be removed.
+    pub dry_run: bool,
+}
+
+/// How `generate` generates passwords. What isn't given comes from the policy, then from the config.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GenerateOptions {
+    pub source: Option<Source>,
+    /// A built-in or configured policy.
+    pub policy: Option<String>,
+    pub length: Option<usize>,
+    pub exclude_chars: Option<String>,
+    pub no_lowercase: bool,
+    pub no_uppercase: bool,
+    pub no_digits: bool,
+    pub no_symbols: bool,
+    pub require_each_type: bool,
+    pub no_ambiguous: bool,
+    /// Generates a passphrase of this many words instead.
+    pub words: Option<usize>,
+    pub separator: Option<String>,
+    /// A file of words for passphrases.
+    pub wordlist: Option<PathBuf>,
+    /// Replaces the password of an existing entry instead, keeping the rest of the entry.
+    pub in_place: bool,
+    /// Overwrites a plain password in place.
+    pub force: bool,
+}
 
 #[async_trait]
 pub trait PassStore {
-    async fn init(&self);
-    async fn list(&self, prefix: Option<&str>);
-    async fn show(&self, name: &str);
-    async fn insert(&self, name: &str);
-    async fn edit(&self, name: &str);
-    async fn generate(&self, name: &str, exclude_chars: Option<&str>, length: Option<&i64>);
-    async fn remove(&self, name: &str);
+    async fn init(&self) -> Result<()>;
+    /// Lists the passwords under **prefix** as a tree, **depth** levels deep when given, or one name per line when
+    /// **flat** is set.
+    async fn list(&self, prefix: Option<&str>, flat: bool, depth: Option<usize>) -> Result<()>;
+    /// Lists the passwords whose names fuzzy match **term**, best match first, then those whose descriptions or tag
+    /// values start with it.
+    async fn find(&self, term: &str) -> Result<()>;
+    /// Shows the lines matching **pattern** of the passwords under **prefix**, after confirming that their values
+    /// are to be decrypted unless **yes** is set.
+    async fn grep(&self, pattern: &Regex, prefix: Option<&str>, yes: bool) -> Result<()>;
+    /// The names of the store's passwords, from the listing cached for the session.
+    async fn names(&self) -> Result<Vec<String>>;
+    /// When the session last used by the store's commands expires, none before one was used. Never asks for an MFA
+    /// token code, an expired session is only renewed by the next command.
+    fn session_expiration(&self) -> Option<DateTime<Utc>>;
+    /// Picks a password with a fuzzy finder over the store's listing, cached for the session unless **refresh** is
+    /// set, then shows it, copies it or its username to the clipboard for **clip_timeout** seconds, edits it or shows
+    /// its OTP code.
+    async fn pick(&self, refresh: bool, clip_timeout: u64) -> Result<()>;
+    async fn show(
+        &self,
+        name: &str,
+        selector: Option<&Selector>,
+        clip_timeout: Option<u64>,
+        version: Option<&str>,
+    ) -> Result<()>;
+    async fn insert(&self, name: &str, mode: InsertMode) -> Result<()>;
+    async fn edit(&self, name: &str) -> Result<()>;
+    /// Generates a password for the new password **name**, or for the existing one in place, see **GenerateOptions**.
+    async fn generate(&self, name: &str, options: &GenerateOptions) -> Result<()>;
+    /// Removes **name**, or every password under it when removing recursively, see **RemoveOptions**. Removing more
+    /// than one password or removing for good is confirmed first.
+    async fn remove(&self, name: &str, options: &RemoveOptions) -> Result<()>;
+    /// Lists the removed passwords pending deletion.
+    async fn trash(&self) -> Result<()>;
+    /// Restores the removed password **name**.
+    async fn restore(&self, name: &str) -> Result<()>;
+    async fn mv(&self, from: &str, to: &str, force: bool) -> Result<()>;
+    /// Copies **from** to **to**, within the store or into the store and region of **dest_settings**.
+    async fn cp(&self, from: &str, to: &str, force: bool, dest_settings: Option<&Settings>) -> Result<()>;
+    /// Lists the versions of **name** with their creation dates and stages, newest first.
+    async fn history(&self, name: &str) -> Result<()>;
+    /// Shows the lines that differ between the versions **from** and **to** of **name**, masking values unless
+    /// **unmask** is set.
+    async fn diff(&self, name: &str, from: &str, to: &str, unmask: bool) -> Result<()>;
+    /// Makes **version** the current version of **name**.
+    async fn rollback(&self, name: &str, version: &str) -> Result<()>;
+    async fn otp(&self, name: &str, clip_timeout: Option<u64>, show_remaining: bool) -> Result<()>;
+    async fn insert_otp(&self, name: &str, force: bool) -> Result<()>;
+    async fn otp_uri(&self, name: &str) -> Result<()>;
+    async fn set_mfa_seed(&self) -> Result<()>;
+    async fn remove_mfa_seed(&self) -> Result<()>;
 }
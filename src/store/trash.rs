@@ -0,0 +1,62 @@
+use crate::util::write_private_file;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+/// A password `remove` scheduled for deletion. Secrets Manager only lists secrets pending deletion to newer clients
+/// than this one, so removals are recorded for `trash` to list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrashRecord {
+    pub store: String,
+    pub region: String,
+    pub name: String,
+    pub arn: String,
+    /// When the password will be deleted for good.
+    pub deletion_date: Option<String>,
+}
+
+/// The records of removed passwords, one JSON object per line.
+pub struct Trash {
+    path: PathBuf,
+}
+
+impl Trash {
+    pub fn new(path: PathBuf) -> Trash {
+        Trash { path }
+    }
+
+    pub fn load(&self) -> Result<Vec<TrashRecord>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let contents = fs::read_to_string(&self.path)?;
+        // Records that don't parse can't be listed or restored from, they're dropped rather than failing
+        Ok(contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Replaces the records with **records**.
+    pub fn save(&self, records: &[TrashRecord]) -> Result<()> {
+        if records.is_empty() {
+            if self.path.exists() {
+                fs::remove_file(&self.path)?;
+            }
+            return Ok(());
+        }
+        let mut contents = String::new();
+        for record in records {
+            contents.push_str(&serde_json::to_string(record)?);
+            contents.push('\n');
+        }
+        Ok(write_private_file(&self.path, contents.as_bytes())?)
+    }
+
+    pub fn add(&self, added: Vec<TrashRecord>) -> Result<()> {
+        let mut records = self.load()?;
+        records.retain(|r| !added.iter().any(|a| a.arn == r.arn));
+        records.extend(added);
+        self.save(&records)
+    }
+}
@@ -0,0 +1,89 @@
+use crate::util::{BLUE_BOLD, RESET};
+use std::collections::BTreeMap;
+
+/// A folder of password names, names holding a `/` are nested in folders.
+#[derive(Debug, Default)]
+pub struct Tree {
+    /// Whether a password has the node's name, a folder can share its name with one.
+    leaf: bool,
+    children: BTreeMap<String, Tree>,
+}
+
+impl Tree {
+    /// Builds the tree of the given **names**.
+    pub fn new<'a, I>(names: I) -> Tree
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut root = Tree::default();
+        for name in names {
+            let mut node = &mut root;
+            for part in name.split('/').filter(|p| !p.is_empty()) {
+                node = node.children.entry(part.to_string()).or_default();
+            }
+            node.leaf = true;
+        }
+        root
+    }
+
+    /// Renders the tree below a **root** line with box-drawing branches, down to **depth** levels when given.
+    /// Folders are colored when **color** is set.
+    pub fn render(&self, root: &str, depth: Option<usize>, color: bool) -> String {
+        let mut out = paint(root, color);
+        out.push('\n');
+        self.render_children("", depth, color, &mut out);
+        out
+    }
+
+    fn render_children(&self, indent: &str, depth: Option<usize>, color: bool, out: &mut String) {
+        if depth == Some(0) {
+            return;
+        }
+        // A password sharing its name with a folder is listed before the folder
+        let entries: Vec<(&String, Option<&Tree>)> = self
+            .children
+            .iter()
+            .flat_map(|(name, child)| {
+                let leaf = (child.leaf && !child.children.is_empty()).then_some((name, None));
+                let node = (name, Some(child).filter(|c| !c.children.is_empty()));
+                leaf.into_iter().chain(std::iter::once(node))
+            })
+            .collect();
+        let count = entries.len();
+        for (i, (name, folder)) in entries.into_iter().enumerate() {
+            let last = i + 1 == count;
+            out.push_str(indent);
+            out.push_str(if last { "└── " } else { "├── " });
+            match folder {
+                None => out.push_str(name),
+                Some(_) => out.push_str(&paint(name, color)),
+            }
+            out.push('\n');
+            if let Some(folder) = folder {
+                let indent = format!("{}{}", indent, if last { "    " } else { "│   " });
+                folder.render_children(&indent, depth.map(|d| d - 1), color, out);
+            }
+        }
+    }
+}
+
+fn paint(s: &str, color: bool) -> String {
+    match color {
+        true => format!("{}{}{}", BLUE_BOLD, s, RESET),
+        false => s.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_password_sharing_name_with_folder() {
+        let tree = Tree::new(["aws", "aws/root", "email"]);
+        assert_eq!(
+            tree.render("Password Store", None, false),
+            "Password Store\n├── aws\n├── aws\n│   └── root\n└── email\n"
+        );
+    }
+}
@@ -1,40 +1,59 @@
 use std::{
-    fs::File,
-    io::{self, BufRead, BufReader, LineWriter, Write},
+    env,
+    fs::{self, File, OpenOptions, Permissions},
+    io::{self, BufRead, BufReader, IsTerminal, Read, Write},
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
     path::PathBuf,
-    process,
 };
 
-pub fn read_first_line(path: &PathBuf) -> Option<String> {
-    let file = File::open(path).unwrap();
+pub const BLUE_BOLD: &str = "\x1b[1;34m";
+pub const RED_BOLD: &str = "\x1b[1;31m";
+pub const RESET: &str = "\x1b[0m";
+
+/// Finds the given **program** in the directories of `$PATH`.
+pub fn find_on_path(program: &str) -> Option<PathBuf> {
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+pub fn read_first_line(path: &PathBuf) -> io::Result<Option<String>> {
+    let file = File::open(path)?;
     let buffer = BufReader::new(file);
-    let mut lines_iter = buffer.lines().map(|l| l.unwrap());
-    return lines_iter.next();
+    buffer.lines().next().transpose()
 }
 
-pub fn prompt_stdin_line(prompt: &str) -> String {
-    println!("{}", prompt);
+/// Reads all of stdin. A single line's trailing newline is dropped, multiple lines are kept as they are.
+pub fn read_stdin_to_end() -> io::Result<String> {
     let mut value = String::new();
-    let stdin = io::stdin();
-    stdin.lock().read_line(&mut value).unwrap();
-    value.trim_end().to_string()
+    io::stdin().read_to_string(&mut value)?;
+    if value.lines().count() == 1 {
+        value.truncate(value.trim_end_matches(&['\r', '\n'][..]).len());
+    }
+    Ok(value)
 }
 
-pub fn prompt_non_empty_str(name: &str) -> String {
-    let line = prompt_stdin_line(&format!("{}:", name));
-    if line.is_empty() {
-        fatal_println!("{} must be non-empty", name);
-    }
-    line
+pub fn stdin_is_tty() -> bool {
+    io::stdin().is_terminal()
+}
+
+pub fn stderr_is_tty() -> bool {
+    io::stderr().is_terminal()
+}
+
+/// Whether to color output, when stdout is a terminal and `NO_COLOR` isn't set.
+pub fn use_color() -> bool {
+    io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none()
 }
 
-pub fn write_lines<'a, I>(path: &PathBuf, lines: I)
-where
-    I: IntoIterator<Item = &'a str>,
-{
-    let file = File::create(path).unwrap();
-    let mut lw = LineWriter::new(file);
-    lines.into_iter().for_each(|line| {
-        lw.write_all(line.as_bytes()).unwrap();
-    });
+/// Writes **contents** to the file at **path**, readable and writable only by its owner.
+pub fn write_private_file(path: &PathBuf, contents: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    fs::set_permissions(path, Permissions::from_mode(0o600))?;
+    file.write_all(contents)
 }